@@ -1,9 +1,23 @@
 use crate::span::Spanned as Sp;
+use std::fmt;
 
 #[derive(Clone, Debug)]
 pub struct Program {
     pub struct_decls: Vec<StructDecl>,
     pub fn_prototypes: Vec<FnPrototype>,
+    pub static_decls: Vec<StaticDecl>,
+
+    /// The analyzed functions: either a single body without a signature, or one or more
+    /// `fn name(args) -> Ty { .. }` bodies, which are analyzed independently.
+    pub bodies: Vec<Body>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Body {
+    /// The signature of the function, if the body is declared as `fn name(args) -> Ty { .. }`.
+    /// Its arguments, and its return place `_0`, are part of the `variables`.
+    pub signature: Option<FnPrototype>,
+
     pub variables: Vec<VariableDecl>,
     pub basic_blocks: Vec<BasicBlock>,
 }
@@ -13,6 +27,15 @@ pub struct StructDecl {
     pub name: Name,
     pub generic_decls: Vec<GenericDecl>,
     pub field_decls: Vec<VariableDecl>,
+    /// The destructor of the struct, if it implements `Drop` (`struct S<'a>: Drop { .. }`).
+    pub drop_impl: Option<DropImpl>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DropImpl {
+    /// The generic parameters the destructor doesn't access, and which are allowed to dangle when
+    /// the struct is dropped (`struct S<#[may_dangle] 'a>: Drop { .. }`).
+    pub may_dangle: Vec<Name>,
 }
 
 #[derive(Clone, Debug)]
@@ -21,6 +44,15 @@ pub struct VariableDecl {
     pub ty: Ty,
 }
 
+/// A global item (`static NAME: Ty;` or `const NAME: Ty;`), whose memory is never freed. Both
+/// kinds are analyzed the same way: they can't be written to, so their loans are never
+/// invalidated.
+#[derive(Clone, Debug)]
+pub struct StaticDecl {
+    pub name: Name,
+    pub ty: Ty,
+}
+
 #[derive(Clone, Debug)]
 pub struct FnPrototype {
     pub name: Name,
@@ -32,16 +64,59 @@ pub struct FnPrototype {
 #[derive(Clone, Debug)]
 pub enum GenericDecl {
     Origin(Name),
-    Ty(Name),
+
+    /// A generic type parameter, and the origins it must outlive: `T: 'a + 'b`.
+    Ty {
+        name: Name,
+        bounds: Vec<Name>,
+    },
 }
 
 #[derive(Clone, Debug)]
 pub struct BasicBlock {
     pub name: Name,
+    /// Whether this block is only executed while unwinding (`bb2 (cleanup): { .. }`).
+    pub is_cleanup: bool,
     pub statements: Vec<Sp<Statement>>,
-    pub successors: Vec<Name>,
+    pub terminator: Terminator,
+}
+
+#[derive(Clone, Debug)]
+pub enum Terminator {
+    /// Continues to any of the successor blocks (`goto bb1, bb2;`), or exits the function when
+    /// there are none. Branching to several successors after an expression statement branches on
+    /// its value, which must be a `bool`, like the conditions of `if`s and `while`s.
+    Goto(Vec<Name>),
+
+    /// Returns from the analyzed function (`return;`): its locals are dead, and the value in the
+    /// return place is passed to the caller.
+    Return,
+
+    /// A call that can unwind (`x = f(y) -> [return: bb1, unwind: bb2];`): continues to the
+    /// `target` block when the callee returns, and to the `unwind` cleanup block when it panics.
+    Call {
+        call: Sp<Statement>,
+        target: Name,
+        unwind: Option<Name>,
+    },
+}
+
+impl BasicBlock {
+    /// The blocks this block continues to, including the cleanup block a call unwinds to.
+    pub fn successors(&self) -> Vec<&Name> {
+        match &self.terminator {
+            Terminator::Goto(successors) => successors.iter().collect(),
+            Terminator::Return => vec![],
+            Terminator::Call { target, unwind, .. } => {
+                std::iter::once(target).chain(unwind).collect()
+            }
+        }
+    }
 }
 
+/// The name of the return place, whose type is the return type of the analyzed function.
+pub const RETURN_PLACE: &str = "_0";
+
 #[derive(Clone, Debug)]
 pub enum Statement {
     /// An assignment (`place = expr;`).
@@ -49,14 +124,100 @@ pub enum Statement {
 
     /// A bare expression (`expr;`).
     Expr(Expr),
+
+    /// Drops the value in a place (`drop(place);`), running the destructors in its type.
+    Drop(Place),
+
+    /// Ends the scope of a local (`StorageDead(x);`): it's implicitly dropped, unless it was
+    /// moved out, and its memory is freed.
+    StorageDead(Name),
 }
 
 #[derive(Clone, Debug)]
 pub enum Expr {
-    Access { kind: AccessKind, place: Place },
-    Number { value: i32 },
-    Call { name: Name, arguments: Vec<Expr> },
+    Access {
+        kind: AccessKind,
+        place: Place,
+    },
+    Number {
+        value: i32,
+    },
+    Call {
+        name: Name,
+        arguments: Vec<Expr>,
+    },
     Unit,
+
+    /// A closure capturing places by shared or mutable borrow, or by move: `|&'L x, move y|`.
+    /// Closures are desugared into a struct of their captures, before emitting facts.
+    Closure {
+        captures: Vec<Expr>,
+    },
+
+    /// A cast of a reference to a raw pointer: `&'L x as *const i32`.
+    Cast {
+        expr: Box<Expr>,
+        ty: Ty,
+    },
+
+    /// A borrow of an rvalue: `&'L 22`, `&'L mut f()`. The rvalue is stored in a `temporary`,
+    /// which is declared before emitting facts.
+    BorrowRvalue {
+        kind: AccessKind,
+        rvalue: Box<Expr>,
+        temporary: Option<Name>,
+
+        /// Whether the temporary lives as long as the variable the borrow initializes, in
+        /// `let x = &temp;`. Otherwise, it's dead at the end of its statement.
+        extended: bool,
+    },
+
+    /// A struct literal, with a value per field: `Pair { a: &'L x, b: move r }`.
+    Struct {
+        name: Name,
+        fields: Vec<(Name, Expr)>,
+    },
+
+    /// An arithmetic, comparison or logical operation on two operands: `copy a + copy b`,
+    /// `copy x < 3`. Like in MIR, the operands are copies, moves or numbers, and are both
+    /// evaluated: `&&` and `||` don't short-circuit.
+    BinaryOp {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+
+    /// A negation of an operand: `-copy x`, `!copy b`.
+    UnaryOp {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Arithmetic negation: `-x`.
+    Neg,
+
+    /// Logical negation of a `bool`, or bitwise negation of an `i32`: `!x`.
+    Not,
 }
 
 #[derive(Clone, Debug)]
@@ -79,26 +240,177 @@ pub enum Ty {
         ty: Box<Ty>,
     },
 
+    /// A raw pointer type, `*const T`: raw pointers carry no origins, their uses are unchecked.
+    RawPtr {
+        ty: Box<Ty>,
+    },
+
+    /// A mutable raw pointer type, `*mut T`.
+    RawPtrMut {
+        ty: Box<Ty>,
+    },
+
     I32,
 
+    Bool,
+
     Unit,
 
     Struct {
         name: Name,
         parameters: Vec<Parameter>,
     },
+
+    /// A generic type parameter, declared by the enclosing struct or function.
+    Param {
+        name: Name,
+    },
+
+    /// A function pointer type, `for<'a> fn(&'a i32) -> &'a i32`. The origins in the `for<..>`
+    /// binder are bound by the type itself, and instantiated each time it is used.
+    FnPtr {
+        binder: Vec<Name>,
+        arg_tys: Vec<Ty>,
+        ret_ty: Box<Ty>,
+    },
+
+    /// A trait object type, `dyn Trait + 'a`. Its concrete type is erased: only the origin
+    /// bounding the origins it contains is known.
+    Dyn {
+        name: Name,
+        origin: Name,
+    },
 }
 
 impl Ty {
-    /// If this is a reference type, returns the type of the target of that reference.
+    /// If this is a reference or raw pointer type, returns the type of the target of that
+    /// reference or pointer.
     pub fn target(&self) -> Option<&Ty> {
         match self {
-            Self::Ref { ty, .. } | Self::RefMut { ty, .. } => Some(&*ty),
+            Self::Ref { ty, .. }
+            | Self::RefMut { ty, .. }
+            | Self::RawPtr { ty }
+            | Self::RawPtrMut { ty } => Some(&*ty),
             _ => None,
         }
     }
+
+    /// Calls `f` on each of the `visited` origins in this type, in order of appearance.
+    pub fn visit_origins(&self, visited: OriginsVisited, f: &mut impl FnMut(&Name)) {
+        walk_origins(self, visited, &mut Vec::new(), f)
+    }
+
+    /// Calls `f` on each of the `visited` origins in this type, like `visit_origins`, to modify
+    /// them.
+    pub fn visit_origins_mut(&mut self, visited: OriginsVisited, f: &mut impl FnMut(&mut Name)) {
+        walk_origins_mut(self, visited, &mut Vec::new(), f)
+    }
+}
+
+/// The origins of a type visited by `Ty::visit_origins`.
+#[derive(Clone, Copy)]
+pub struct OriginsVisited {
+    /// Whether the origins behind raw pointers are visited: they're unchecked.
+    pub behind_raw_ptrs: bool,
+
+    /// Which origins of function pointer types are visited.
+    pub fn_ptrs: FnPtrOrigins,
+}
+
+impl OriginsVisited {
+    /// All the origins written in the type.
+    pub const ALL: Self = Self {
+        behind_raw_ptrs: true,
+        fn_ptrs: FnPtrOrigins::All,
+    };
+}
+
+#[derive(Clone, Copy)]
+pub enum FnPtrOrigins {
+    /// None of the origins in function pointer types.
+    None,
+
+    /// The free origins of function pointer types, and not the ones bound by their binder.
+    Free,
+
+    /// All the origins of function pointer types, including the ones declared by their binder.
+    All,
+}
+
+// Generates a walk over the origins of a type, from a shared or a mutable reference to it. The
+// origins bound by the binders of the enclosing function pointer types are in `bound`.
+macro_rules! walk_origins {
+    ($name:ident, $($mutability:tt)?) => {
+        fn $name(
+            ty: &$($mutability)? Ty,
+            visited: OriginsVisited,
+            bound: &mut Vec<Name>,
+            f: &mut impl FnMut(&$($mutability)? Name),
+        ) {
+            match ty {
+                Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
+                    if !bound.contains(origin) {
+                        f(origin);
+                    }
+                    $name(ty, visited, bound, f);
+                }
+
+                Ty::RawPtr { ty } | Ty::RawPtrMut { ty } => {
+                    if visited.behind_raw_ptrs {
+                        $name(ty, visited, bound, f);
+                    }
+                }
+
+                Ty::Struct { parameters, .. } => {
+                    for parameter in parameters {
+                        match parameter {
+                            Parameter::Origin(origin) => {
+                                if !bound.contains(origin) {
+                                    f(origin);
+                                }
+                            }
+                            Parameter::Ty(ty) => $name(ty, visited, bound, f),
+                        }
+                    }
+                }
+
+                Ty::FnPtr {
+                    binder,
+                    arg_tys,
+                    ret_ty,
+                } => {
+                    let bound_count = bound.len();
+                    match visited.fn_ptrs {
+                        FnPtrOrigins::None => return,
+                        FnPtrOrigins::Free => bound.extend(binder.iter().cloned()),
+                        FnPtrOrigins::All => {
+                            for origin in binder {
+                                f(origin);
+                            }
+                        }
+                    }
+                    for ty in arg_tys {
+                        $name(ty, visited, bound, f);
+                    }
+                    $name(ret_ty, visited, bound, f);
+                    bound.truncate(bound_count);
+                }
+
+                Ty::Dyn { origin, .. } => {
+                    if !bound.contains(origin) {
+                        f(origin);
+                    }
+                }
+
+                Ty::Param { .. } | Ty::I32 | Ty::Bool | Ty::Unit => {}
+            }
+        }
+    };
 }
 
+walk_origins!(walk_origins,);
+walk_origins!(walk_origins_mut, mut);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Parameter {
     Origin(Name),
@@ -121,4 +433,24 @@ pub struct Place {
     pub projections: Vec<Projection>,
 }
 
+/// Displays the place in the syntax of the programs, e.g. `(*p).f`.
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = self.base.clone();
+        for projection in &self.projections {
+            text = match projection {
+                Projection::Deref => format!("*{}", text),
+                Projection::Field(field) if text.starts_with('*') => {
+                    format!("({}).{}", text, field)
+                }
+                Projection::Field(field) => format!("{}.{}", text, field),
+            };
+        }
+        write!(f, "{}", text)
+    }
+}
+
 pub type Name = String;
+
+/// The origin of references that are valid for the entire program.
+pub const STATIC_ORIGIN: &str = "'static";
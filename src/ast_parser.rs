@@ -12,7 +12,10 @@
 use std::str::FromStr;
 
 use crate::ast;
+use crate::elision::{elide_signature_origins, resolve_elided_origins, ELIDED_ORIGIN};
+use crate::lowering::{lower_body, Block, Stmt};
 use crate::span::{Span, Spanned as Sp, WithSpan};
+use crate::subst::resolve_generic_params;
 
 #[cfg(test)]
 pub(crate) mod test;
@@ -22,17 +25,95 @@ peg::parser! {
         pub rule program() -> ast::Program = (
             _ struct_decls:struct_decl()**__ _
             fn_prototypes:fn_prototype()**__ _
-            variables:var_decl()**__ _
-            basic_blocks:basic_block()**__ _ {
+            static_decls:static_decl()**__ _
+            bodies:(fn_body()++__ / body:body() { vec![body] }) _ {
                 ast::Program {
                     struct_decls,
                     fn_prototypes,
-                    variables,
-                    basic_blocks,
+                    static_decls,
+                    bodies,
                 }
             }
         )
 
+        // The body of an analyzed function, with its signature: the arguments are variables,
+        // and so is the return place `_0`, whose type is the return type.
+        rule fn_body() -> ast::Body = (
+            "fn" _ name:ident() _ generic_decls:generic_decls() _
+            "(" _ arg_decls:field_decl()**comma() _ ")" _ "->" _ ret_ty:ty() _
+            "{" _ body:body() _ "}" {
+                let (mut generic_decls, mut arg_decls, mut ret_ty) = (generic_decls, arg_decls, ret_ty);
+                elide_signature_origins(&mut generic_decls, &mut arg_decls, &mut ret_ty);
+
+                let ast::Body { variables: locals, basic_blocks, .. } = body;
+                let return_place = ast::VariableDecl {
+                    name: ast::RETURN_PLACE.to_string(),
+                    ty: ret_ty.clone(),
+                };
+                let signature = ast::FnPrototype {
+                    name,
+                    generic_decls,
+                    arg_tys: arg_decls.iter().map(|a| a.ty.clone()).collect(),
+                    ret_ty,
+                };
+                let variables = Some(return_place).into_iter().chain(arg_decls).chain(locals).collect();
+                ast::Body { signature: Some(signature), variables, basic_blocks }
+            }
+        )
+
+        rule body() -> ast::Body = (
+            variables:var_decl()**__ _ basic_blocks:basic_block()**__ &(_ ("}" / ![_])) {
+                ast::Body { signature: None, variables, basic_blocks }
+            } /
+            stmts:structured_statement()**__ _ {?
+                let (variables, basic_blocks) = lower_body(stmts)?;
+                Ok(ast::Body { signature: None, variables, basic_blocks })
+            }
+        )
+
+        // The structured syntax, lowered to basic blocks: `let`s, `if`/`else`, `while` and `loop`
+        // loops, `break`s, and `{ .. }` scopes.
+        rule structured_statement() -> Stmt = (
+            let_statement() /
+            if_statement() /
+            "while" __ cond:sp(<expr()>) _ body:block() {
+                Stmt::While { cond: Sp::map(cond, ast::Statement::Expr), body }
+            } /
+            "loop" _ body:block() { Stmt::Loop { body } } /
+            start:position!() "break" _ ";" end:position!() { Stmt::Break(Span::new(start, end)) } /
+            block:block() { Stmt::Block(block) } /
+            statement:sp(<statement()>) { Stmt::Statement(statement) }
+        )
+
+        rule let_statement() -> Stmt = (
+            start:position!() "let" __ name:ident() _ ":" _ ty:ty() _
+            init:("=" _ expr:expr() _ { expr })? ";" end:position!() {
+                let place = ast::Place { base: name.clone(), projections: vec![] };
+                let init = init.map(|expr| ast::Statement::Assign(place, expr).at(Span::new(start, end)));
+                Stmt::Let { decl: ast::VariableDecl { name, ty }, init }
+            }
+        )
+
+        rule if_statement() -> Stmt = (
+            "if" __ cond:sp(<expr()>) _ then_block:block() else_block:(_ "else" _ b:else_block() { b })? {
+                Stmt::If { cond: Sp::map(cond, ast::Statement::Expr), then_block, else_block }
+            }
+        )
+
+        // The `else` block, or an `else if`, which is the only statement of its block
+        rule else_block() -> Block = (
+            block() /
+            stmt:if_statement() end:position!() {
+                Block { stmts: vec![stmt], end: Span::new(end, end) }
+            }
+        )
+
+        rule block() -> Block = (
+            "{" _ stmts:structured_statement()**__ _ start:position!() "}" end:position!() {
+                Block { stmts, end: Span::new(start, end) }
+            }
+        )
+
         rule whitespace() -> () = [' ' | '\n']
         rule comment() -> () = "//" [^'\n']* "\n" { () }
         rule skip() -> () = whitespace() / comment()
@@ -40,15 +121,40 @@ peg::parser! {
         rule __ = quiet!{skip()+}
 
         rule struct_decl() -> ast::StructDecl = (
-            "struct" _ name:ident() _ generic_decls:generic_decls() _
-            "{" _ field_decls:field_decl()**comma() _ comma()? "}" {
-                ast::StructDecl { name, generic_decls, field_decls }
+            "struct" _ name:ident() _ generic_decls:struct_generic_decls() _
+            is_drop:(":" _ "Drop" _)? "{" _ field_decls:field_decl()**comma() _ comma()? "}" {?
+                let may_dangle: Vec<_> = generic_decls
+                    .iter()
+                    .filter(|(may_dangle, _)| *may_dangle)
+                    .map(|(_, decl)| match decl {
+                        ast::GenericDecl::Origin(name) | ast::GenericDecl::Ty { name, .. } => name.clone(),
+                    })
+                    .collect();
+                let drop_impl = match is_drop {
+                    Some(_) => Some(ast::DropImpl { may_dangle }),
+                    None if may_dangle.is_empty() => None,
+                    None => return Err("a `Drop` impl for `#[may_dangle]` parameters"),
+                };
+                let generic_decls = generic_decls.into_iter().map(|(_, decl)| decl).collect();
+                Ok(ast::StructDecl { name, generic_decls, field_decls, drop_impl })
             }
         )
 
+        rule struct_generic_decls() -> Vec<(bool, ast::GenericDecl)> = (
+            "<" _ g:struct_generic_decl()**comma() _ ">" { g } /
+            () { vec![] }
+        )
+
+        rule struct_generic_decl() -> (bool, ast::GenericDecl) = (
+            may_dangle:("#[may_dangle]" _)? decl:generic_decl() { (may_dangle.is_some(), decl) }
+        )
+
         rule fn_prototype() -> ast::FnPrototype = (
             "fn" _ name:ident() _ generic_decls:generic_decls() _
             "(" _ arg_decls:field_decl()**comma() _ ")" _ "->" _ ret_ty:ty() _ ";" {
+                let (mut generic_decls, mut arg_decls, mut ret_ty) = (generic_decls, arg_decls, ret_ty);
+                elide_signature_origins(&mut generic_decls, &mut arg_decls, &mut ret_ty);
+
                 let arg_tys = arg_decls.into_iter().map(|a| a.ty).collect();
                 ast::FnPrototype { name, generic_decls, arg_tys, ret_ty }
             }
@@ -61,35 +167,75 @@ peg::parser! {
 
         rule generic_decl() -> ast::GenericDecl = (
             o:origin_ident() { ast::GenericDecl::Origin(o) } /
-            n:ident() { ast::GenericDecl::Ty(n) }
+            name:ident() _ bounds:outlives_bounds() { ast::GenericDecl::Ty { name, bounds } }
+        )
+
+        rule outlives_bounds() -> Vec<ast::Name> = (
+            ":" _ b:origin_ident()++(_ "+" _) { b } /
+            () { vec![] }
         )
 
         rule field_decl() -> ast::VariableDecl = name:ident() _ ":" _ ty:ty() {
             ast::VariableDecl { name, ty }
         }
 
+        rule static_decl() -> ast::StaticDecl = (
+            ("static" / "const") _ name:ident() _ ":" _ ty:ty() _ ";" {
+                ast::StaticDecl { name, ty }
+            }
+        )
+
         rule var_decl() -> ast::VariableDecl = "let" _ name:ident() _ ":" _ ty:ty() _ ";" {
             ast::VariableDecl { name, ty }
         }
 
-        rule ty() -> ast::Ty = ref_mut_ty() / ref_ty() / i32_ty() / unit_ty() / struct_ty()
+        rule ty() -> ast::Ty = (
+            ref_mut_ty() / ref_ty() / raw_ptr_mut_ty() / raw_ptr_ty() / i32_ty() / bool_ty() / unit_ty() / fn_ptr_ty() / dyn_ty() / struct_ty()
+        )
 
-        rule ref_ty() -> ast::Ty = "&" _ origin:origin_ident() _ ty:ty() {
+        rule ref_ty() -> ast::Ty = "&" _ origin:elidable_origin() _ ty:ty() {
             ast::Ty::Ref { origin, ty: Box::new(ty) }
         }
 
-        rule ref_mut_ty() -> ast::Ty = "&" _ origin:origin_ident() _ "mut" _ ty:ty() {
+        rule ref_mut_ty() -> ast::Ty = "&" _ origin:elidable_origin() _ mut_keyword() _ ty:ty() {
             ast::Ty::RefMut { origin, ty: Box::new(ty) }
         }
 
+        rule raw_ptr_ty() -> ast::Ty = "*" _ "const" _ ty:ty() {
+            ast::Ty::RawPtr { ty: Box::new(ty) }
+        }
+
+        rule raw_ptr_mut_ty() -> ast::Ty = "*" _ "mut" _ ty:ty() {
+            ast::Ty::RawPtrMut { ty: Box::new(ty) }
+        }
+
         rule i32_ty() -> ast::Ty = "i32" {
             ast::Ty::I32
         }
 
+        rule bool_ty() -> ast::Ty = "bool" !ident_char() {
+            ast::Ty::Bool
+        }
+
         rule unit_ty() -> ast::Ty = "(" _ ")" {
             ast::Ty::Unit
         }
 
+        rule fn_ptr_ty() -> ast::Ty = (
+            binder:binder() _ "fn" _ "(" _ arg_tys:ty()**comma() _ ")" _ "->" _ ret_ty:ty() {
+                ast::Ty::FnPtr { binder, arg_tys, ret_ty: Box::new(ret_ty) }
+            }
+        )
+
+        rule dyn_ty() -> ast::Ty = "dyn" __ name:ident() _ "+" _ origin:origin_ident() {
+            ast::Ty::Dyn { name, origin }
+        }
+
+        rule binder() -> Vec<ast::Name> = (
+            "for" _ "<" _ o:origin_ident()**comma() _ ">" { o } /
+            () { vec![] }
+        )
+
         rule struct_ty() -> ast::Ty = name:ident() parameters:parameters() {
             ast::Ty::Struct { name, parameters }
         }
@@ -107,28 +253,105 @@ peg::parser! {
         rule comma() -> () = _ "," _ { }
 
         rule basic_block() -> ast::BasicBlock = (
-            name:ident() _ ":" _ "{" _ statements:sp(<statement()>)**__ _ successors:goto() _ "}" {
-                ast::BasicBlock { name, statements, successors }
+            name:ident() _ is_cleanup:cleanup() ":" _ "{" _ statements:sp(<statement()>)**__ _ terminator:terminator() _ "}" {
+                ast::BasicBlock { name, is_cleanup, statements, terminator }
             }
         )
 
-        rule goto() -> Vec<ast::Name> = (
-            "goto" _ names:ident()**comma() _ ";" { names } /
-            () { vec![] }
+        rule cleanup() -> bool = (
+            "(" _ "cleanup" _ ")" _ { true } /
+            () { false }
+        )
+
+        rule terminator() -> ast::Terminator = (
+            "return" _ ";" { ast::Terminator::Return } /
+            call:sp(<call_statement()>) _ "->" _ "[" _ "return" _ ":" _ target:ident() unwind:(comma() "unwind" _ ":" _ u:ident() { u })? _ "]" _ ";" {
+                ast::Terminator::Call { call, target, unwind }
+            } /
+            "goto" _ names:ident()**comma() _ ";" { ast::Terminator::Goto(names) } /
+            () { ast::Terminator::Goto(vec![]) }
         )
 
         rule statement() -> ast::Statement = (
+            "drop" _ "(" _ place:place() _ ")" _ ";" { ast::Statement::Drop(place) } /
+            "StorageDead" _ "(" _ name:ident() _ ")" _ ";" { ast::Statement::StorageDead(name) } /
             place:place() _ "=" _ expr:expr() _ ";" { ast::Statement::Assign(place, expr) } /
             expr:expr() _ ";" { ast::Statement::Expr(expr) }
         )
 
+        rule call_statement() -> ast::Statement = (
+            place:place() _ "=" _ expr:call() { ast::Statement::Assign(place, expr) } /
+            expr:call() { ast::Statement::Expr(expr) }
+        )
+
+        rule call() -> ast::Expr = name:ident() _ "(" _ arguments:expr()**comma() _ ")" {
+            ast::Expr::Call { name, arguments }
+        }
+
         rule expr() -> ast::Expr = (
-            kind:access_kind() _ place:place() { ast::Expr::Access { kind, place } } /
-            n:$(['0'..='9']+) { ast::Expr::Number { value: i32::from_str(n).unwrap() } } /
-            name:ident() _ "(" _ arguments:expr()**comma() _ ")" { ast::Expr::Call { name, arguments} } /
+            lhs:operand() _ op:binary_op() _ rhs:operand() {
+                ast::Expr::BinaryOp { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }
+            } /
+            op:unary_op() _ operand:operand() {
+                ast::Expr::UnaryOp { op, operand: Box::new(operand) }
+            } /
+            expr:access() _ "as" _ ty:ty() { ast::Expr::Cast { expr: Box::new(expr), ty } } /
+            kind:borrow_kind() _ rvalue:rvalue() {
+                ast::Expr::BorrowRvalue { kind, rvalue: Box::new(rvalue), temporary: None, extended: false }
+            } /
+            access() /
+            struct_literal() /
+            rvalue() /
+            "|" _ captures:access()**comma() _ "|" { ast::Expr::Closure { captures } }
+        )
+
+        rule struct_literal() -> ast::Expr = (
+            name:ident() _ "{" _ fields:field_value()**comma() _ comma()? "}" {
+                ast::Expr::Struct { name, fields }
+            }
+        )
+
+        rule field_value() -> (ast::Name, ast::Expr) = name:ident() _ ":" _ expr:expr() {
+            (name, expr)
+        }
+
+        // The operands of operators are read: they are copies, moves, or numbers
+        rule operand() -> ast::Expr = (
+            kind:read_kind() _ place:place() { ast::Expr::Access { kind, place } } /
+            number()
+        )
+
+        rule binary_op() -> ast::BinaryOp = (
+            "==" { ast::BinaryOp::Eq } /
+            "!=" { ast::BinaryOp::Ne } /
+            "<=" { ast::BinaryOp::Le } /
+            ">=" { ast::BinaryOp::Ge } /
+            "<" { ast::BinaryOp::Lt } /
+            ">" { ast::BinaryOp::Gt } /
+            "&&" { ast::BinaryOp::And } /
+            "||" { ast::BinaryOp::Or } /
+            "+" { ast::BinaryOp::Add } /
+            "-" { ast::BinaryOp::Sub } /
+            "*" { ast::BinaryOp::Mul } /
+            "/" { ast::BinaryOp::Div } /
+            "%" { ast::BinaryOp::Rem }
+        )
+
+        rule unary_op() -> ast::UnaryOp = (
+            "-" { ast::UnaryOp::Neg } /
+            "!" { ast::UnaryOp::Not }
+        )
+
+        rule rvalue() -> ast::Expr = (
+            number() /
+            call() /
             "(" _ ")" { ast::Expr::Unit }
         )
 
+        rule access() -> ast::Expr = kind:access_kind() _ place:place() {
+            ast::Expr::Access { kind, place }
+        }
+
         pub rule place() -> ast::Place = precedence!{
             "*" _ inner:@ {
                 let mut inner = inner;
@@ -146,21 +369,40 @@ peg::parser! {
             "(" _ inner:place() _ ")" { inner }
         }
 
-        rule access_kind() -> ast::AccessKind = (
+        rule number() -> ast::Expr = n:$(['0'..='9']+) {
+            ast::Expr::Number { value: i32::from_str(n).unwrap() }
+        }
+
+        rule access_kind() -> ast::AccessKind = read_kind() / borrow_kind()
+
+        rule read_kind() -> ast::AccessKind = (
             "copy" { ast::AccessKind::Copy } /
-            "move" { ast::AccessKind::Move } /
-            "&" _ o:origin_ident() _ "mut" { ast::AccessKind::BorrowMut(o) } /
-            "&" _ o:origin_ident() { ast::AccessKind::Borrow(o) }
+            "move" { ast::AccessKind::Move }
+        )
+
+        rule borrow_kind() -> ast::AccessKind = (
+            "&" _ o:elidable_origin() _ mut_keyword() { ast::AccessKind::BorrowMut(o) } /
+            "&" _ o:elidable_origin() { ast::AccessKind::Borrow(o) }
         )
 
-        rule ident() -> ast::Name = t:$(['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9' ]+) {
+        rule mut_keyword() = "mut" !ident_char()
+
+        rule ident() -> ast::Name = t:$(ident_char()+) {
             t.to_string()
         }
 
+        rule ident_char() = ['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9']
+
         rule origin_ident() -> ast::Name = t:$("'"['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9' | '*' ]+) {
             t.to_string()
         }
 
+        // An origin which can be elided, and is then given a fresh name after parsing.
+        rule elidable_origin() -> ast::Name = (
+            origin_ident() /
+            () { ELIDED_ORIGIN.to_string() }
+        )
+
         rule sp<T>(t: rule<T>) -> Sp<T> = start:position!() inner:t() end:position!() {
             inner.at(Span::new(start, end))
         }
@@ -168,7 +410,10 @@ peg::parser! {
 }
 
 pub fn parse_ast(input: &str) -> eyre::Result<ast::Program> {
-    Ok(ast_parser::program(input)?)
+    let mut program = ast_parser::program(input)?;
+    resolve_generic_params(&mut program);
+    resolve_elided_origins(&mut program)?;
+    Ok(program)
 }
 
 pub use self::ast_parser::place;
@@ -2,7 +2,11 @@ use super::*;
 
 pub fn expect_parse(s: &str) -> ast::Program {
     match super::ast_parser::program(s) {
-        Ok(p) => p,
+        Ok(mut p) => {
+            resolve_generic_params(&mut p);
+            resolve_elided_origins(&mut p).unwrap();
+            p
+        }
         Err(e) => {
             let offset = e.location.offset;
             panic!(
@@ -27,13 +31,19 @@ fn let_test() {
     Program {
         struct_decls: [],
         fn_prototypes: [],
-        variables: [
-            VariableDecl {
-                name: "x",
-                ty: I32,
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [
+                    VariableDecl {
+                        name: "x",
+                        ty: I32,
+                    },
+                ],
+                basic_blocks: [],
             },
         ],
-        basic_blocks: [],
     }
     "###);
 }
@@ -52,28 +62,37 @@ fn statement_test() {
     Program {
         struct_decls: [],
         fn_prototypes: [],
-        variables: [],
-        basic_blocks: [
-            BasicBlock {
-                name: "bb0",
-                statements: [
-                    Spanned {
-                        span: Span {
-                            start: 28,
-                            end: 35,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "x",
-                                projections: [],
-                            },
-                            Number {
-                                value: 22,
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [],
+                basic_blocks: [
+                    BasicBlock {
+                        name: "bb0",
+                        is_cleanup: false,
+                        statements: [
+                            Spanned {
+                                span: Span {
+                                    start: 28,
+                                    end: 35,
+                                },
+                                inner: Assign(
+                                    Place {
+                                        base: "x",
+                                        projections: [],
+                                    },
+                                    Number {
+                                        value: 22,
+                                    },
+                                ),
                             },
+                        ],
+                        terminator: Goto(
+                            [],
                         ),
                     },
                 ],
-                successors: [],
             },
         ],
     }
@@ -100,84 +119,99 @@ fn borrow_test() {
     Program {
         struct_decls: [],
         fn_prototypes: [],
-        variables: [],
-        basic_blocks: [
-            BasicBlock {
-                name: "bb0",
-                statements: [
-                    Spanned {
-                        span: Span {
-                            start: 28,
-                            end: 35,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "x",
-                                projections: [],
-                            },
-                            Number {
-                                value: 22,
-                            },
-                        ),
-                    },
-                    Spanned {
-                        span: Span {
-                            start: 48,
-                            end: 58,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "y",
-                                projections: [],
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [],
+                basic_blocks: [
+                    BasicBlock {
+                        name: "bb0",
+                        is_cleanup: false,
+                        statements: [
+                            Spanned {
+                                span: Span {
+                                    start: 28,
+                                    end: 35,
+                                },
+                                inner: Assign(
+                                    Place {
+                                        base: "x",
+                                        projections: [],
+                                    },
+                                    Number {
+                                        value: 22,
+                                    },
+                                ),
                             },
-                            Access {
-                                kind: Borrow(
-                                    "'y",
+                            Spanned {
+                                span: Span {
+                                    start: 48,
+                                    end: 58,
+                                },
+                                inner: Assign(
+                                    Place {
+                                        base: "y",
+                                        projections: [],
+                                    },
+                                    Access {
+                                        kind: Borrow(
+                                            "'y",
+                                        ),
+                                        place: Place {
+                                            base: "x",
+                                            projections: [],
+                                        },
+                                    },
                                 ),
-                                place: Place {
-                                    base: "x",
-                                    projections: [],
+                            },
+                            Spanned {
+                                span: Span {
+                                    start: 71,
+                                    end: 85,
                                 },
+                                inner: Assign(
+                                    Place {
+                                        base: "z",
+                                        projections: [],
+                                    },
+                                    Access {
+                                        kind: BorrowMut(
+                                            "'z",
+                                        ),
+                                        place: Place {
+                                            base: "x",
+                                            projections: [],
+                                        },
+                                    },
+                                ),
                             },
+                        ],
+                        terminator: Goto(
+                            [
+                                "bb1",
+                                "bb2",
+                            ],
                         ),
                     },
-                    Spanned {
-                        span: Span {
-                            start: 71,
-                            end: 85,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "z",
-                                projections: [],
-                            },
-                            Access {
-                                kind: BorrowMut(
-                                    "'z",
-                                ),
-                                place: Place {
-                                    base: "x",
-                                    projections: [],
-                                },
-                            },
+                    BasicBlock {
+                        name: "bb1",
+                        is_cleanup: false,
+                        statements: [],
+                        terminator: Goto(
+                            [],
+                        ),
+                    },
+                    BasicBlock {
+                        name: "bb2",
+                        is_cleanup: false,
+                        statements: [],
+                        terminator: Goto(
+                            [],
                         ),
                     },
-                ],
-                successors: [
-                    "bb1",
-                    "bb2",
                 ],
             },
-            BasicBlock {
-                name: "bb1",
-                statements: [],
-                successors: [],
-            },
-            BasicBlock {
-                name: "bb2",
-                statements: [],
-                successors: [],
-            },
         ],
     }
     "###);
@@ -202,79 +236,88 @@ fn copy_move_test() {
     Program {
         struct_decls: [],
         fn_prototypes: [],
-        variables: [
-            VariableDecl {
-                name: "x",
-                ty: I32,
-            },
-            VariableDecl {
-                name: "y",
-                ty: I32,
-            },
-            VariableDecl {
-                name: "z",
-                ty: I32,
-            },
-        ],
-        basic_blocks: [
-            BasicBlock {
-                name: "bb0",
-                statements: [
-                    Spanned {
-                        span: Span {
-                            start: 88,
-                            end: 95,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "x",
-                                projections: [],
-                            },
-                            Number {
-                                value: 22,
-                            },
-                        ),
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [
+                    VariableDecl {
+                        name: "x",
+                        ty: I32,
                     },
-                    Spanned {
-                        span: Span {
-                            start: 108,
-                            end: 119,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "y",
-                                projections: [],
-                            },
-                            Access {
-                                kind: Copy,
-                                place: Place {
-                                    base: "x",
-                                    projections: [],
+                    VariableDecl {
+                        name: "y",
+                        ty: I32,
+                    },
+                    VariableDecl {
+                        name: "z",
+                        ty: I32,
+                    },
+                ],
+                basic_blocks: [
+                    BasicBlock {
+                        name: "bb0",
+                        is_cleanup: false,
+                        statements: [
+                            Spanned {
+                                span: Span {
+                                    start: 88,
+                                    end: 95,
                                 },
+                                inner: Assign(
+                                    Place {
+                                        base: "x",
+                                        projections: [],
+                                    },
+                                    Number {
+                                        value: 22,
+                                    },
+                                ),
                             },
-                        ),
-                    },
-                    Spanned {
-                        span: Span {
-                            start: 132,
-                            end: 143,
-                        },
-                        inner: Assign(
-                            Place {
-                                base: "z",
-                                projections: [],
+                            Spanned {
+                                span: Span {
+                                    start: 108,
+                                    end: 119,
+                                },
+                                inner: Assign(
+                                    Place {
+                                        base: "y",
+                                        projections: [],
+                                    },
+                                    Access {
+                                        kind: Copy,
+                                        place: Place {
+                                            base: "x",
+                                            projections: [],
+                                        },
+                                    },
+                                ),
                             },
-                            Access {
-                                kind: Move,
-                                place: Place {
-                                    base: "x",
-                                    projections: [],
+                            Spanned {
+                                span: Span {
+                                    start: 132,
+                                    end: 143,
                                 },
+                                inner: Assign(
+                                    Place {
+                                        base: "z",
+                                        projections: [],
+                                    },
+                                    Access {
+                                        kind: Move,
+                                        place: Place {
+                                            base: "x",
+                                            projections: [],
+                                        },
+                                    },
+                                ),
                             },
+                        ],
+                        terminator: Goto(
+                            [],
                         ),
                     },
                 ],
-                successors: [],
             },
         ],
     }
@@ -299,9 +342,10 @@ fn struct_test() {
                     Origin(
                         "'me",
                     ),
-                    Ty(
-                        "T",
-                    ),
+                    Ty {
+                        name: "T",
+                        bounds: [],
+                    },
                 ],
                 field_decls: [
                     VariableDecl {
@@ -312,9 +356,8 @@ fn struct_test() {
                                 name: "Vec",
                                 parameters: [
                                     Ty(
-                                        Struct {
+                                        Param {
                                             name: "T",
-                                            parameters: [],
                                         },
                                     ),
                                 ],
@@ -326,28 +369,36 @@ fn struct_test() {
                         ty: I32,
                     },
                 ],
+                drop_impl: None,
             },
             StructDecl {
                 name: "Vec",
                 generic_decls: [
-                    Ty(
-                        "T",
-                    ),
+                    Ty {
+                        name: "T",
+                        bounds: [],
+                    },
                 ],
                 field_decls: [
                     VariableDecl {
                         name: "item0",
-                        ty: Struct {
+                        ty: Param {
                             name: "T",
-                            parameters: [],
                         },
                     },
                 ],
+                drop_impl: None,
             },
         ],
         fn_prototypes: [],
-        variables: [],
-        basic_blocks: [],
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [],
+                basic_blocks: [],
+            },
+        ],
     }
     "###);
 }
@@ -367,19 +418,20 @@ fn fn_test() {
             StructDecl {
                 name: "Vec",
                 generic_decls: [
-                    Ty(
-                        "T",
-                    ),
+                    Ty {
+                        name: "T",
+                        bounds: [],
+                    },
                 ],
                 field_decls: [
                     VariableDecl {
                         name: "element",
-                        ty: Struct {
+                        ty: Param {
                             name: "T",
-                            parameters: [],
                         },
                     },
                 ],
+                drop_impl: None,
             },
         ],
         fn_prototypes: [
@@ -389,9 +441,10 @@ fn fn_test() {
                     Origin(
                         "'v",
                     ),
-                    Ty(
-                        "T",
-                    ),
+                    Ty {
+                        name: "T",
+                        bounds: [],
+                    },
                 ],
                 arg_tys: [
                     RefMut {
@@ -400,28 +453,354 @@ fn fn_test() {
                             name: "Vec",
                             parameters: [
                                 Ty(
-                                    Struct {
+                                    Param {
                                         name: "T",
-                                        parameters: [],
                                     },
                                 ),
                             ],
                         },
                     },
-                    Struct {
+                    Param {
                         name: "T",
-                        parameters: [],
                     },
                 ],
                 ret_ty: Unit,
             },
         ],
-        variables: [],
-        basic_blocks: [],
+        static_decls: [],
+        bodies: [
+            Body {
+                signature: None,
+                variables: [],
+                basic_blocks: [],
+            },
+        ],
     }
     "###);
 }
 
+#[test]
+fn generic_bounds_test() {
+    let p = expect_parse(
+        "
+        struct Ref<'a, T: 'a> { r: &'a T }
+        fn f<'a, 'b, T: 'a + 'b, U>(t: T, u: U) -> ();
+    ",
+    );
+
+    insta::assert_debug_snapshot!((&p.struct_decls[0].generic_decls, &p.fn_prototypes[0].generic_decls), @r###"
+    (
+        [
+            Origin(
+                "'a",
+            ),
+            Ty {
+                name: "T",
+                bounds: [
+                    "'a",
+                ],
+            },
+        ],
+        [
+            Origin(
+                "'a",
+            ),
+            Origin(
+                "'b",
+            ),
+            Ty {
+                name: "T",
+                bounds: [
+                    "'a",
+                    "'b",
+                ],
+            },
+            Ty {
+                name: "U",
+                bounds: [],
+            },
+        ],
+    )
+    "###);
+}
+
+#[test]
+fn static_test() {
+    let p = expect_parse(
+        "
+        static S: i32;
+        const C: &'static i32;
+    ",
+    );
+
+    insta::assert_debug_snapshot!(p.static_decls, @r###"
+    [
+        StaticDecl {
+            name: "S",
+            ty: I32,
+        },
+        StaticDecl {
+            name: "C",
+            ty: Ref {
+                origin: "'static",
+                ty: I32,
+            },
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn fn_ptr_test() {
+    let p = expect_parse(
+        "
+        let f: fn(i32) -> ();
+        let g: for<'a> fn(&'a i32, &'b i32) -> &'a i32;
+    ",
+    );
+
+    insta::assert_debug_snapshot!(p.bodies[0].variables, @r###"
+    [
+        VariableDecl {
+            name: "f",
+            ty: FnPtr {
+                binder: [],
+                arg_tys: [
+                    I32,
+                ],
+                ret_ty: Unit,
+            },
+        },
+        VariableDecl {
+            name: "g",
+            ty: FnPtr {
+                binder: [
+                    "'a",
+                ],
+                arg_tys: [
+                    Ref {
+                        origin: "'a",
+                        ty: I32,
+                    },
+                    Ref {
+                        origin: "'b",
+                        ty: I32,
+                    },
+                ],
+                ret_ty: Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+            },
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn dyn_test() {
+    let p = expect_parse(
+        "
+        let b: Box<dyn Debug + 'b>;
+        let p: &'p dyn Debug + 'static;
+    ",
+    );
+
+    insta::assert_debug_snapshot!(p.bodies[0].variables, @r###"
+    [
+        VariableDecl {
+            name: "b",
+            ty: Struct {
+                name: "Box",
+                parameters: [
+                    Ty(
+                        Dyn {
+                            name: "Debug",
+                            origin: "'b",
+                        },
+                    ),
+                ],
+            },
+        },
+        VariableDecl {
+            name: "p",
+            ty: Ref {
+                origin: "'p",
+                ty: Dyn {
+                    name: "Debug",
+                    origin: "'static",
+                },
+            },
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn closure_test() {
+    let p = expect_parse(
+        "
+        bb0: {
+            c = |&'L_x x, &'L_y mut y, move z|;
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(*p.bodies[0].basic_blocks[0].statements[0], @r###"
+    Assign(
+        Place {
+            base: "c",
+            projections: [],
+        },
+        Closure {
+            captures: [
+                Access {
+                    kind: Borrow(
+                        "'L_x",
+                    ),
+                    place: Place {
+                        base: "x",
+                        projections: [],
+                    },
+                },
+                Access {
+                    kind: BorrowMut(
+                        "'L_y",
+                    ),
+                    place: Place {
+                        base: "y",
+                        projections: [],
+                    },
+                },
+                Access {
+                    kind: Move,
+                    place: Place {
+                        base: "z",
+                        projections: [],
+                    },
+                },
+            ],
+        },
+    )
+    "###);
+}
+
+#[test]
+fn raw_ptr_test() {
+    let p = expect_parse(
+        "
+        let p: *const i32;
+        let q: *mut &'q i32;
+        bb0: {
+            p = &'L_x x as *const i32;
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!((&p.bodies[0].variables, &*p.bodies[0].basic_blocks[0].statements[0]), @r###"
+    (
+        [
+            VariableDecl {
+                name: "p",
+                ty: RawPtr {
+                    ty: I32,
+                },
+            },
+            VariableDecl {
+                name: "q",
+                ty: RawPtrMut {
+                    ty: Ref {
+                        origin: "'q",
+                        ty: I32,
+                    },
+                },
+            },
+        ],
+        Assign(
+            Place {
+                base: "p",
+                projections: [],
+            },
+            Cast {
+                expr: Access {
+                    kind: Borrow(
+                        "'L_x",
+                    ),
+                    place: Place {
+                        base: "x",
+                        projections: [],
+                    },
+                },
+                ty: RawPtr {
+                    ty: I32,
+                },
+            },
+        ),
+    )
+    "###);
+}
+
+#[test]
+fn fn_body_test() {
+    let p = expect_parse(
+        "
+        fn f<'a>(x: &'a i32) -> &'a i32 {
+            let y: i32;
+            bb0: {
+                _0 = copy x;
+                return;
+            }
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!((&p.bodies[0].signature, &p.bodies[0].variables, &p.bodies[0].basic_blocks[0].terminator), @r###"
+    (
+        Some(
+            FnPrototype {
+                name: "f",
+                generic_decls: [
+                    Origin(
+                        "'a",
+                    ),
+                ],
+                arg_tys: [
+                    Ref {
+                        origin: "'a",
+                        ty: I32,
+                    },
+                ],
+                ret_ty: Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+            },
+        ),
+        [
+            VariableDecl {
+                name: "_0",
+                ty: Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+            },
+            VariableDecl {
+                name: "x",
+                ty: Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+            },
+            VariableDecl {
+                name: "y",
+                ty: I32,
+            },
+        ],
+        Return,
+    )
+    "###);
+}
+
 #[test]
 fn example_vec_temp() {
     let program = "
@@ -476,3 +855,795 @@ fn example_issue_47680() {
     ";
     insta::assert_debug_snapshot!(expect_parse(program));
 }
+
+#[test]
+fn call_terminator_test() {
+    let p = expect_parse(
+        "
+        fn f(x: i32) -> i32;
+        let x: i32;
+        bb0: {
+            x = f(copy x) -> [return: bb1, unwind: bb2];
+        }
+
+        bb1: {
+            f(copy x) -> [return: bb0];
+        }
+
+        bb2 (cleanup): {
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(
+        p.bodies[0].basic_blocks
+            .iter()
+            .map(|bb| (bb.is_cleanup, bb.successors()))
+            .collect::<Vec<_>>(),
+        @r###"
+    [
+        (
+            false,
+            [
+                "bb1",
+                "bb2",
+            ],
+        ),
+        (
+            false,
+            [
+                "bb0",
+            ],
+        ),
+        (
+            true,
+            [],
+        ),
+    ]
+    "###
+    );
+}
+
+#[test]
+fn drop_test() {
+    let p = expect_parse(
+        "
+        struct Vec<#[may_dangle] T, 'a>: Drop { item0: T, r: &'a i32 }
+        let v: Vec<i32, 'v>;
+        bb0: {
+            drop(v.item0);
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(
+        (&p.struct_decls[0].drop_impl, &*p.bodies[0].basic_blocks[0].statements[0]),
+        @r###"
+    (
+        Some(
+            DropImpl {
+                may_dangle: [
+                    "T",
+                ],
+            },
+        ),
+        Drop(
+            Place {
+                base: "v",
+                projections: [
+                    Field(
+                        "item0",
+                    ),
+                ],
+            },
+        ),
+    )
+    "###
+    );
+}
+
+#[test]
+fn borrow_rvalue_test() {
+    let p = expect_parse(
+        "
+        bb0: {
+            a = &'a 22;
+            b = &'b mut f(&'c ());
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(
+        p.bodies[0].basic_blocks[0].statements.iter().map(|s| &**s).collect::<Vec<_>>(),
+        @r###"
+    [
+        Assign(
+            Place {
+                base: "a",
+                projections: [],
+            },
+            BorrowRvalue {
+                kind: Borrow(
+                    "'a",
+                ),
+                rvalue: Number {
+                    value: 22,
+                },
+                temporary: None,
+                extended: false,
+            },
+        ),
+        Assign(
+            Place {
+                base: "b",
+                projections: [],
+            },
+            BorrowRvalue {
+                kind: BorrowMut(
+                    "'b",
+                ),
+                rvalue: Call {
+                    name: "f",
+                    arguments: [
+                        BorrowRvalue {
+                            kind: Borrow(
+                                "'c",
+                            ),
+                            rvalue: Unit,
+                            temporary: None,
+                            extended: false,
+                        },
+                    ],
+                },
+                temporary: None,
+                extended: false,
+            },
+        ),
+    ]
+    "###
+    );
+}
+
+#[test]
+fn struct_literal_test() {
+    let p = expect_parse(
+        "
+        bb0: {
+            s = Pair { a: &'L1 x, b: Pair { a: move r, b: 1, }, };
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(&*p.bodies[0].basic_blocks[0].statements[0], @r###"
+    Assign(
+        Place {
+            base: "s",
+            projections: [],
+        },
+        Struct {
+            name: "Pair",
+            fields: [
+                (
+                    "a",
+                    Access {
+                        kind: Borrow(
+                            "'L1",
+                        ),
+                        place: Place {
+                            base: "x",
+                            projections: [],
+                        },
+                    },
+                ),
+                (
+                    "b",
+                    Struct {
+                        name: "Pair",
+                        fields: [
+                            (
+                                "a",
+                                Access {
+                                    kind: Move,
+                                    place: Place {
+                                        base: "r",
+                                        projections: [],
+                                    },
+                                },
+                            ),
+                            (
+                                "b",
+                                Number {
+                                    value: 1,
+                                },
+                            ),
+                        ],
+                    },
+                ),
+            ],
+        },
+    )
+    "###);
+}
+
+#[test]
+fn elided_origins_test() {
+    let p = expect_parse(
+        "
+        struct S<'a> {
+            r: &'a i32,
+        }
+        static X: &i32;
+
+        let p: &&mut i32;
+        let s: S<'_>;
+        let q: &'p i32;
+
+        bb0: {
+            p = &mut x;
+            q = &'_ *p;
+            p = &mutable;
+            s = S { r: &22 };
+            q = &(*p).f;
+        }
+    ",
+    );
+
+    let tys: Vec<_> = p
+        .static_decls
+        .iter()
+        .map(|s| &s.ty)
+        .chain(p.bodies[0].variables.iter().map(|v| &v.ty))
+        .collect();
+    insta::assert_debug_snapshot!(tys, @r###"
+    [
+        Ref {
+            origin: "'static",
+            ty: I32,
+        },
+        Ref {
+            origin: "'p2",
+            ty: RefMut {
+                origin: "'p3",
+                ty: I32,
+            },
+        },
+        Struct {
+            name: "S",
+            parameters: [
+                Origin(
+                    "'s",
+                ),
+            ],
+        },
+        Ref {
+            origin: "'p",
+            ty: I32,
+        },
+    ]
+    "###);
+
+    let statements: Vec<_> = p.bodies[0].basic_blocks[0]
+        .statements
+        .iter()
+        .map(|s| &**s)
+        .collect();
+    insta::assert_debug_snapshot!(statements, @r###"
+    [
+        Assign(
+            Place {
+                base: "p",
+                projections: [],
+            },
+            Access {
+                kind: BorrowMut(
+                    "'L_x",
+                ),
+                place: Place {
+                    base: "x",
+                    projections: [],
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "q",
+                projections: [],
+            },
+            Access {
+                kind: Borrow(
+                    "'L_*p",
+                ),
+                place: Place {
+                    base: "p",
+                    projections: [
+                        Deref,
+                    ],
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "p",
+                projections: [],
+            },
+            Access {
+                kind: Borrow(
+                    "'L_mutable",
+                ),
+                place: Place {
+                    base: "mutable",
+                    projections: [],
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "s",
+                projections: [],
+            },
+            Struct {
+                name: "S",
+                fields: [
+                    (
+                        "r",
+                        BorrowRvalue {
+                            kind: Borrow(
+                                "'L_22",
+                            ),
+                            rvalue: Number {
+                                value: 22,
+                            },
+                            temporary: None,
+                            extended: false,
+                        },
+                    ),
+                ],
+            },
+        ),
+        Assign(
+            Place {
+                base: "q",
+                projections: [],
+            },
+            Access {
+                kind: Borrow(
+                    "'L_*p_f",
+                ),
+                place: Place {
+                    base: "p",
+                    projections: [
+                        Deref,
+                        Field(
+                            "f",
+                        ),
+                    ],
+                },
+            },
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn elided_origins_in_signatures_test() {
+    let p = expect_parse(
+        "
+        fn single(x: &i32, y: i32) -> &mut S<'_>;
+        fn inputs<'a>(x: &'a i32, y: &'a i32, z: S<'_, i32>) -> ();
+        fn method<'1>(self: &mut S<'1>, x: &i32) -> &i32;
+        fn static_input(x: &'static i32) -> &i32;
+        fn nested<'a>(x: &'a &'a i32) -> &i32;
+        fn f(x: &i32) -> &i32 {
+            bb0: {}
+        }
+    ",
+    );
+    let signatures: Vec<_> = p
+        .fn_prototypes
+        .iter()
+        .chain(&p.bodies[0].signature)
+        .map(|proto| {
+            let mut tys = proto.arg_tys.clone();
+            tys.push(proto.ret_ty.clone());
+            (&proto.name, &proto.generic_decls, tys)
+        })
+        .collect();
+    insta::assert_debug_snapshot!(signatures, @r###"
+    [
+        (
+            "single",
+            [
+                Origin(
+                    "'1",
+                ),
+            ],
+            [
+                Ref {
+                    origin: "'1",
+                    ty: I32,
+                },
+                I32,
+                RefMut {
+                    origin: "'1",
+                    ty: Struct {
+                        name: "S",
+                        parameters: [
+                            Origin(
+                                "'1",
+                            ),
+                        ],
+                    },
+                },
+            ],
+        ),
+        (
+            "inputs",
+            [
+                Origin(
+                    "'a",
+                ),
+                Origin(
+                    "'1",
+                ),
+            ],
+            [
+                Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+                Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+                Struct {
+                    name: "S",
+                    parameters: [
+                        Origin(
+                            "'1",
+                        ),
+                        Ty(
+                            I32,
+                        ),
+                    ],
+                },
+                Unit,
+            ],
+        ),
+        (
+            "method",
+            [
+                Origin(
+                    "'1",
+                ),
+                Origin(
+                    "'2",
+                ),
+                Origin(
+                    "'3",
+                ),
+            ],
+            [
+                RefMut {
+                    origin: "'2",
+                    ty: Struct {
+                        name: "S",
+                        parameters: [
+                            Origin(
+                                "'1",
+                            ),
+                        ],
+                    },
+                },
+                Ref {
+                    origin: "'3",
+                    ty: I32,
+                },
+                Ref {
+                    origin: "'2",
+                    ty: I32,
+                },
+            ],
+        ),
+        (
+            "static_input",
+            [],
+            [
+                Ref {
+                    origin: "'static",
+                    ty: I32,
+                },
+                Ref {
+                    origin: "'static",
+                    ty: I32,
+                },
+            ],
+        ),
+        (
+            "nested",
+            [
+                Origin(
+                    "'a",
+                ),
+            ],
+            [
+                Ref {
+                    origin: "'a",
+                    ty: Ref {
+                        origin: "'a",
+                        ty: I32,
+                    },
+                },
+                Ref {
+                    origin: "'a",
+                    ty: I32,
+                },
+            ],
+        ),
+        (
+            "f",
+            [
+                Origin(
+                    "'1",
+                ),
+            ],
+            [
+                Ref {
+                    origin: "'1",
+                    ty: I32,
+                },
+                Ref {
+                    origin: "'1",
+                    ty: I32,
+                },
+            ],
+        ),
+    ]
+    "###);
+
+    // The analyzed function's arguments are typed by its signature
+    let tys: Vec<_> = p.bodies[0]
+        .variables
+        .iter()
+        .map(|v| (&v.name, &v.ty))
+        .collect();
+    insta::assert_debug_snapshot!(tys, @r###"
+    [
+        (
+            "_0",
+            Ref {
+                origin: "'1",
+                ty: I32,
+            },
+        ),
+        (
+            "x",
+            Ref {
+                origin: "'1",
+                ty: I32,
+            },
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn ambiguous_elided_origins_test() {
+    for program in [
+        "fn f(x: &i32, y: &i32) -> &i32;",
+        "fn f() -> &i32;",
+        "fn f(x: i32) -> &i32;",
+        "fn f(x: &i32, self: &i32) -> &i32;",
+        "fn f(x: &&i32) -> &i32;",
+        "fn f<'a>(x: &'a i32, y: &'a i32) -> &i32;",
+        "fn f<'a>(x: &'a i32, y: &'a i32) -> &i32 { bb0: {} }",
+    ] {
+        let error = parse_ast(program).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Can't elide the origins in the return type of `f`: there must be a `self` \
+             reference, or a single argument with a single origin"
+        );
+    }
+
+    let program = "fn f(x: fn(&i32) -> ()) -> ();";
+    let error = parse_ast(program).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Missing origin in a function pointer type in the signature of `f`"
+    );
+
+    let program = "
+        struct S {
+            r: &i32,
+        }
+    ";
+    let error = parse_ast(program).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Missing origin in the type of the field `r` of struct `S`"
+    );
+}
+
+#[test]
+fn operators_test() {
+    let p = expect_parse(
+        "
+        let b: bool;
+        bb0: {
+            x = copy a % 2;
+            b = move c != copy *d.e;
+            b = !copy b;
+            x = -1;
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(&p.bodies[0].variables[0].ty, @r###"
+    Bool
+    "###);
+    let statements: Vec<_> = p.bodies[0].basic_blocks[0]
+        .statements
+        .iter()
+        .map(|s| &**s)
+        .collect();
+    insta::assert_debug_snapshot!(statements, @r###"
+    [
+        Assign(
+            Place {
+                base: "x",
+                projections: [],
+            },
+            BinaryOp {
+                op: Rem,
+                lhs: Access {
+                    kind: Copy,
+                    place: Place {
+                        base: "a",
+                        projections: [],
+                    },
+                },
+                rhs: Number {
+                    value: 2,
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "b",
+                projections: [],
+            },
+            BinaryOp {
+                op: Ne,
+                lhs: Access {
+                    kind: Move,
+                    place: Place {
+                        base: "c",
+                        projections: [],
+                    },
+                },
+                rhs: Access {
+                    kind: Copy,
+                    place: Place {
+                        base: "d",
+                        projections: [
+                            Field(
+                                "e",
+                            ),
+                            Deref,
+                        ],
+                    },
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "b",
+                projections: [],
+            },
+            UnaryOp {
+                op: Not,
+                operand: Access {
+                    kind: Copy,
+                    place: Place {
+                        base: "b",
+                        projections: [],
+                    },
+                },
+            },
+        ),
+        Assign(
+            Place {
+                base: "x",
+                projections: [],
+            },
+            UnaryOp {
+                op: Neg,
+                operand: Number {
+                    value: 1,
+                },
+            },
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn structured_syntax_errors_test() {
+    let error = parse_ast("break;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "error at 1:7: expected a `break` inside of a loop"
+    );
+
+    let error = parse_ast("let x: i32; { let x: i32; }").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "error at 1:28: expected a new variable name, shadowing is not supported"
+    );
+}
+
+#[test]
+fn multiple_bodies_test() {
+    let p = expect_parse(
+        "
+        fn callee(x: &i32) -> &i32 {
+            _0 = copy x;
+        }
+
+        fn caller() -> () {
+            let x: i32;
+            bb0: {
+                callee(&x);
+            }
+        }
+    ",
+    );
+
+    let bodies: Vec<_> = p
+        .bodies
+        .iter()
+        .map(|body| {
+            let name = &body.signature.as_ref().unwrap().name;
+            let variables: Vec<_> = body.variables.iter().map(|v| (&v.name, &v.ty)).collect();
+            (name, variables, body.basic_blocks.len())
+        })
+        .collect();
+    insta::assert_debug_snapshot!(bodies, @r###"
+    [
+        (
+            "callee",
+            [
+                (
+                    "_0",
+                    Ref {
+                        origin: "'1",
+                        ty: I32,
+                    },
+                ),
+                (
+                    "x",
+                    Ref {
+                        origin: "'1",
+                        ty: I32,
+                    },
+                ),
+            ],
+            1,
+        ),
+        (
+            "caller",
+            [
+                (
+                    "_0",
+                    Unit,
+                ),
+                (
+                    "x",
+                    I32,
+                ),
+            ],
+            1,
+        ),
+    ]
+    "###);
+}
@@ -0,0 +1,402 @@
+//! Elided origins, which are replaced by fresh origins after parsing.
+//!
+//! Like in Rust, origins can be elided in the types of local variables (`let p: &i32;`,
+//! `let s: S<'_>;`) and in borrows (`&x`, `&mut *p`). Each elided position is given a fresh
+//! origin, whose name is derived from where it appears, so that the facts stay readable and
+//! stable:
+//! - `'p` in the type of the variable `p`
+//! - `'L_x` for a borrow of `x`, `'L_*p` for a borrow of `*p`, and `'L_s_f` for a borrow of the
+//!   field `s.f`
+//! - `'L_22`, `'L_f`, or `'L_unit`, for a borrow of the rvalue `22`, `f(..)`, or `()`
+//!
+//! Names are never reused within a function: when a name is already taken, e.g. by the first
+//! borrow of `x` or by an origin written out in the program, the next ones are numbered: `'L_x2`,
+//! `'L_x3`, etc.
+//!
+//! In the types of statics and constants, elided origins are `'static`. In function signatures,
+//! the elision rules are applied when parsing, see `elide_signature_origins`.
+
+use crate::ast::*;
+use std::collections::HashSet;
+
+/// The origin written in place of an elided origin: `&'_ i32` is the same as `&i32`.
+pub const ELIDED_ORIGIN: &str = "'_";
+
+// The origins of a signature whose elided positions are resolved by the elision rules: the
+// origins of function pointer types are bound by these types.
+const SIGNATURE_ORIGINS: OriginsVisited = OriginsVisited {
+    fn_ptrs: FnPtrOrigins::None,
+    ..OriginsVisited::ALL
+};
+
+/// Replaces the elided origins of the program with fresh origins, or with `'static` in the types
+/// of statics.
+pub fn resolve_elided_origins(program: &mut Program) -> eyre::Result<()> {
+    for decl in &program.struct_decls {
+        for field in &decl.field_decls {
+            if has_elided_origins(&field.ty) {
+                eyre::bail!(
+                    "Missing origin in the type of the field `{}` of struct `{}`",
+                    field.name,
+                    decl.name,
+                );
+            }
+        }
+    }
+
+    // The elision rules were applied to the signatures when parsing them: the elided origins
+    // left are the ones of function pointer types, and the ambiguous ones without a single input
+    // origin to be elided from.
+    let signatures = program
+        .bodies
+        .iter()
+        .filter_map(|body| body.signature.as_ref());
+    for proto in program.fn_prototypes.iter().chain(signatures) {
+        let mut tys = proto.arg_tys.iter().chain(Some(&proto.ret_ty));
+        if tys.any(has_elided_fn_ptr_origins) {
+            eyre::bail!(
+                "Missing origin in a function pointer type in the signature of `{}`",
+                proto.name
+            );
+        }
+        if has_elided_origins(&proto.ret_ty) {
+            eyre::bail!(
+                "Can't elide the origins in the return type of `{}`: there must be a `self` \
+                 reference, or a single argument with a single origin",
+                proto.name
+            );
+        }
+    }
+
+    for decl in &mut program.static_decls {
+        decl.ty
+            .visit_origins_mut(OriginsVisited::ALL, &mut |origin| {
+                if origin == ELIDED_ORIGIN {
+                    *origin = STATIC_ORIGIN.to_string();
+                }
+            });
+    }
+
+    // The functions are analyzed independently: their fresh origins only need to be distinct
+    // from the ones of their own body.
+    for body_idx in 0..program.bodies.len() {
+        let mut fresh_origins = FreshOrigins::new(program, body_idx);
+        resolve_elided_origins_in_body(&mut program.bodies[body_idx], &mut fresh_origins)?;
+    }
+
+    Ok(())
+}
+
+fn resolve_elided_origins_in_body(
+    body: &mut Body,
+    fresh_origins: &mut FreshOrigins,
+) -> eyre::Result<()> {
+    // The return place and the arguments of the function are typed by its signature, which has
+    // no elided origins.
+    let signature_variable_count = match &body.signature {
+        Some(signature) => signature.arg_tys.len() + 1,
+        None => 0,
+    };
+    for variable in &mut body.variables[signature_variable_count..] {
+        // TODO: the elided origins of function pointers are bound by the function pointer type,
+        // like in function signatures. Until then: only support elided origins outside of
+        // function pointer types.
+        if has_elided_fn_ptr_origins(&variable.ty) {
+            eyre::bail!(
+                "Missing origin in the function pointer type of `{}`",
+                variable.name
+            );
+        }
+
+        let name = format!("'{}", variable.name);
+        variable
+            .ty
+            .visit_origins_mut(OriginsVisited::ALL, &mut |origin| {
+                if origin == ELIDED_ORIGIN {
+                    *origin = fresh_origins.fresh(&name);
+                }
+            });
+    }
+
+    for bb in &mut body.basic_blocks {
+        let call = match &mut bb.terminator {
+            Terminator::Call { call, .. } => Some(call),
+            _ => None,
+        };
+        for statement in bb.statements.iter_mut().chain(call) {
+            match &mut **statement {
+                Statement::Assign(_, expr) | Statement::Expr(expr) => {
+                    fresh_origins.resolve_loans_in_expr(expr)
+                }
+                Statement::Drop(_) | Statement::StorageDead(_) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies the elision rules to the signature of a function, like rustc:
+/// - each elided origin in the arguments is a fresh generic origin, named `'1`, `'2`, etc.
+/// - the elided origins in the return type are the origin of the first argument, if it's a `self`
+///   reference, or else the origin of the only argument with origins, if it has a single distinct
+///   origin, e.g. `'a` in `x: &'a &'a i32`. Otherwise, they are ambiguous, and are left elided.
+///
+/// The origins in function pointer types are bound by these types, and are not elided here.
+pub fn elide_signature_origins(
+    generic_decls: &mut Vec<GenericDecl>,
+    arg_decls: &mut [VariableDecl],
+    ret_ty: &mut Ty,
+) {
+    // The distinct origins of each argument which has origins
+    let mut input_origins: Vec<Vec<Name>> = Vec::new();
+    for arg in arg_decls.iter_mut() {
+        let mut origins = Vec::new();
+        arg.ty.visit_origins_mut(SIGNATURE_ORIGINS, &mut |origin| {
+            if origin == ELIDED_ORIGIN {
+                *origin = (1..)
+                    .map(|idx| format!("'{}", idx))
+                    .find(|candidate| {
+                        generic_decls
+                            .iter()
+                            .all(|decl| !matches!(decl, GenericDecl::Origin(o) if o == candidate))
+                    })
+                    .expect("There are infinitely many candidates");
+                generic_decls.push(GenericDecl::Origin(origin.clone()));
+            }
+            if !origins.contains(origin) {
+                origins.push(origin.clone());
+            }
+        });
+        if !origins.is_empty() {
+            input_origins.push(origins);
+        }
+    }
+
+    let output_origin = match arg_decls.first() {
+        Some(VariableDecl {
+            name,
+            ty: Ty::Ref { origin, .. } | Ty::RefMut { origin, .. },
+        }) if name == "self" => origin,
+        _ => match input_origins.as_slice() {
+            [origins] if origins.len() == 1 => &origins[0],
+            _ => return,
+        },
+    };
+    ret_ty.visit_origins_mut(SIGNATURE_ORIGINS, &mut |origin| {
+        if origin == ELIDED_ORIGIN {
+            *origin = output_origin.clone();
+        }
+    });
+}
+
+/// The origin names used by the declarations of the program and by one of its bodies, and the
+/// fresh ones created for the elided origins of that body.
+struct FreshOrigins {
+    used: HashSet<Name>,
+}
+
+impl FreshOrigins {
+    fn new(program: &Program, body_idx: usize) -> Self {
+        let mut used = HashSet::new();
+        used.insert(STATIC_ORIGIN.to_string());
+
+        let mut use_origin = |origin: &Name| {
+            used.insert(origin.clone());
+        };
+
+        let generic_decls = program
+            .struct_decls
+            .iter()
+            .map(|decl| &decl.generic_decls)
+            .chain(
+                program
+                    .fn_prototypes
+                    .iter()
+                    .map(|proto| &proto.generic_decls),
+            )
+            .chain(
+                program
+                    .bodies
+                    .iter()
+                    .filter_map(|body| body.signature.as_ref())
+                    .map(|proto| &proto.generic_decls),
+            );
+        for decl in generic_decls.flatten() {
+            match decl {
+                GenericDecl::Origin(origin) => use_origin(origin),
+                GenericDecl::Ty { bounds, .. } => bounds.iter().for_each(&mut use_origin),
+            }
+        }
+
+        let field_tys = program
+            .struct_decls
+            .iter()
+            .flat_map(|decl| decl.field_decls.iter().map(|field| &field.ty));
+        let signature_tys = program
+            .fn_prototypes
+            .iter()
+            .chain(
+                program
+                    .bodies
+                    .iter()
+                    .filter_map(|body| body.signature.as_ref()),
+            )
+            .flat_map(|proto| proto.arg_tys.iter().chain(Some(&proto.ret_ty)));
+        let tys = field_tys
+            .chain(signature_tys)
+            .chain(program.static_decls.iter().map(|decl| &decl.ty));
+        for ty in tys {
+            ty.visit_origins(OriginsVisited::ALL, &mut use_origin);
+        }
+
+        let body = &program.bodies[body_idx];
+        for variable in &body.variables {
+            variable
+                .ty
+                .visit_origins(OriginsVisited::ALL, &mut use_origin);
+        }
+
+        for bb in &body.basic_blocks {
+            let call = match &bb.terminator {
+                Terminator::Call { call, .. } => Some(call),
+                _ => None,
+            };
+            for statement in bb.statements.iter().chain(call) {
+                if let Statement::Assign(_, expr) | Statement::Expr(expr) = &**statement {
+                    visit_loans(expr, &mut |origin, _| use_origin(origin));
+                }
+            }
+        }
+
+        used.remove(ELIDED_ORIGIN);
+        FreshOrigins { used }
+    }
+
+    // Returns `name` if it's not used yet, or the first numbered variant of `name` which isn't.
+    fn fresh(&mut self, name: &str) -> Name {
+        let fresh = std::iter::once(name.to_string())
+            .chain((2..).map(|idx| format!("{}{}", name, idx)))
+            .find(|candidate| !self.used.contains(candidate))
+            .expect("There are infinitely many candidates");
+        self.used.insert(fresh.clone());
+        fresh
+    }
+
+    fn resolve_loans_in_expr(&mut self, expr: &mut Expr) {
+        visit_loans_mut(expr, &mut |origin, borrowed| {
+            if origin == ELIDED_ORIGIN {
+                let name = match borrowed {
+                    Borrowed::Place(place) => place_name(place),
+                    Borrowed::Rvalue(rvalue) => rvalue_name(rvalue),
+                };
+                *origin = self.fresh(&format!("'L_{}", name));
+            }
+        });
+    }
+}
+
+// The name of a place, as used in the names of its loans: `*p` for a deref, and `s_f` for the
+// field `s.f`.
+fn place_name(place: &Place) -> String {
+    let mut name = place.base.clone();
+    for projection in &place.projections {
+        match projection {
+            Projection::Deref => name.insert(0, '*'),
+            Projection::Field(field) => {
+                name.push('_');
+                name.push_str(field);
+            }
+        }
+    }
+    name
+}
+
+// The name of a borrowed rvalue, as used in the names of its loans.
+fn rvalue_name(rvalue: &Expr) -> String {
+    match rvalue {
+        Expr::Number { value } => value.to_string(),
+        Expr::Call { name, .. } => name.clone(),
+        Expr::Unit => "unit".to_string(),
+        _ => "rvalue".to_string(),
+    }
+}
+
+fn has_elided_origins(ty: &Ty) -> bool {
+    let mut elided = false;
+    ty.visit_origins(OriginsVisited::ALL, &mut |origin| {
+        elided |= origin == ELIDED_ORIGIN
+    });
+    elided
+}
+
+fn has_elided_fn_ptr_origins(ty: &Ty) -> bool {
+    match ty {
+        Ty::Ref { ty, .. } | Ty::RefMut { ty, .. } | Ty::RawPtr { ty } | Ty::RawPtrMut { ty } => {
+            has_elided_fn_ptr_origins(ty)
+        }
+        Ty::Struct { parameters, .. } => parameters.iter().any(|parameter| match parameter {
+            Parameter::Ty(ty) => has_elided_fn_ptr_origins(ty),
+            Parameter::Origin(_) => false,
+        }),
+        Ty::FnPtr { .. } => has_elided_origins(ty),
+        Ty::Param { .. } | Ty::Dyn { .. } | Ty::I32 | Ty::Bool | Ty::Unit => false,
+    }
+}
+
+// What a loan borrows: a place, or the temporary holding a borrowed rvalue.
+enum Borrowed<'a> {
+    Place(&'a Place),
+    Rvalue(&'a Expr),
+}
+
+// Generates a walk over the origins of the loans in an expression, from a shared or a mutable
+// reference to it, calling `f` on each origin with what the loan borrows. The loans of a borrowed
+// rvalue are visited before the loan of its temporary.
+macro_rules! walk_loans {
+    ($name:ident, $($mutability:tt)?) => {
+        fn $name(expr: &$($mutability)? Expr, f: &mut impl FnMut(&$($mutability)? Name, Borrowed)) {
+            match expr {
+                Expr::Access { kind, place } => {
+                    if let AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin) = kind {
+                        f(origin, Borrowed::Place(place));
+                    }
+                }
+
+                Expr::BorrowRvalue { kind, rvalue, .. } => {
+                    $name(rvalue, f);
+                    if let AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin) = kind {
+                        f(origin, Borrowed::Rvalue(rvalue));
+                    }
+                }
+
+                Expr::Call { arguments, .. }
+                | Expr::Closure {
+                    captures: arguments,
+                } => {
+                    for argument in arguments {
+                        $name(argument, f);
+                    }
+                }
+
+                Expr::Cast { expr, .. } | Expr::UnaryOp { operand: expr, .. } => $name(expr, f),
+
+                Expr::BinaryOp { lhs, rhs, .. } => {
+                    $name(lhs, f);
+                    $name(rhs, f);
+                }
+
+                Expr::Struct { fields, .. } => {
+                    for (_, expr) in fields {
+                        $name(expr, f);
+                    }
+                }
+
+                Expr::Number { .. } | Expr::Unit => {}
+            }
+        }
+    };
+}
+
+walk_loans!(visit_loans,);
+walk_loans!(visit_loans_mut, mut);
@@ -6,16 +6,22 @@ mod test;
 #[cfg(test)]
 mod examples;
 
+mod closures;
+mod drops;
+mod temporaries;
+
 use crate::ast::*;
 use crate::ast_parser::parse_ast;
+use crate::span::Spanned as Sp;
+use crate::subst::Substitution;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::ops::ControlFlow;
+use temporaries::temporary_borrow;
 
 #[derive(Default, PartialEq, Eq, Clone)]
 struct Origin(String);
 
-#[derive(Default, PartialEq, Eq, Clone)]
+#[derive(Default, PartialEq, Eq, Hash, Clone)]
 struct Node(String);
 
 impl<S> From<S> for Origin
@@ -51,31 +57,63 @@ impl fmt::Debug for Node {
 #[derive(Default, Debug)]
 pub(crate) struct Facts {
     access_origin: Vec<(Origin, Node)>,
+    drop_access_origin: Vec<(Origin, Node)>,
     cfg_edge: Vec<(Node, Node)>,
     clear_origin: Vec<(Origin, Node)>,
     introduce_subset: Vec<(Origin, Origin, Node)>,
     invalidate_origin: Vec<(Origin, Node)>,
+    known_subset: Vec<(Origin, Origin)>,
+    mark_as_loan_origin: Vec<Origin>,
     node_text: Vec<(String, Node)>,
+
+    /// The nodes of cleanup blocks, which only run while unwinding.
+    cleanup_node: Vec<Node>,
+
+    /// The loan origin, the borrowed place and the mode of the loans, at the node issuing them.
+    loan_issued_at: Vec<(Origin, String, LoanMode, Node)>,
 }
 
+// Emits the facts of each function of the program, analyzed independently: the name of the
+// function, if its body has a signature, and its facts.
 #[allow(dead_code)]
-fn emit_facts(input: &str) -> eyre::Result<Facts> {
+fn emit_facts(input: &str) -> eyre::Result<Vec<(Option<Name>, Facts)>> {
     let program = parse_ast(input)?;
-    let emitter = FactEmitter::new(program, input, false);
-    let mut facts = Default::default();
-    emitter.emit_facts(&mut facts);
-    Ok(facts)
+    let functions = (0..program.bodies.len())
+        .map(|body_idx| {
+            let emitter = FactEmitter::new(program.clone(), body_idx, input, false);
+            let name = emitter.body.signature.as_ref().map(|s| s.name.clone());
+            let mut facts = Default::default();
+            emitter.emit_facts(&mut facts);
+            (name, facts)
+        })
+        .collect();
+    Ok(functions)
 }
 
 // An internal representation of a `Node`, a location in the CFG: the block within the program,
 // and the statement within that block. Used to analyze locations (e.g. reachability), whereas
 // `Node`s are user-readable representations for facts.
-#[allow(dead_code)]
 struct Location {
     block_idx: usize,
     statement_idx: usize,
 }
 
+// Whether a loan is issued by a shared or a mutable borrow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LoanMode {
+    Shared,
+    Mutable,
+}
+
+impl fmt::Display for LoanMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoanMode::Shared => write!(f, "shared"),
+            LoanMode::Mutable => write!(f, "mutable"),
+        }
+    }
+}
+
 impl From<(usize, usize)> for Location {
     fn from((block_idx, statement_idx): (usize, usize)) -> Self {
         Self {
@@ -88,108 +126,447 @@ impl From<(usize, usize)> for Location {
 struct FactEmitter<'a> {
     input: &'a str,
     program: Program,
-    loans: HashMap<Place, Vec<(Origin, Location)>>,
+
+    /// The body of the analyzed function, one of the program's `bodies`.
+    body: Body,
+
+    loans: HashMap<Place, Vec<(Origin, Location, LoanMode)>>,
+
+    /// The locals moved out as a whole on every path reaching each node.
+    moved_out: HashMap<Node, Vec<Name>>,
+
     simple_node_names: bool,
 }
 
 impl<'a> FactEmitter<'a> {
-    fn new(program: Program, input: &'a str, simple_node_names: bool) -> Self {
-        // Collect loans from borrow expressions present in the program
-        let mut loans: HashMap<Place, Vec<(Origin, Location)>> = HashMap::new();
+    fn new(program: Program, body_idx: usize, input: &'a str, simple_node_names: bool) -> Self {
+        let body = program.bodies[body_idx].clone();
+        let mut emitter = Self {
+            input,
+            program,
+            body,
+            loans: HashMap::new(),
+            moved_out: HashMap::new(),
+            simple_node_names,
+        };
 
-        for (block_idx, bb) in program.basic_blocks.iter().enumerate() {
-            for (statement_idx, s) in bb.statements.iter().enumerate() {
-                let (Statement::Assign(_, expr) | Statement::Expr(expr)) = &**s;
+        // Closures are desugared first, and the temporaries of borrowed rvalues declared: their
+        // captures and borrows are borrow expressions issuing loans.
+        emitter.desugar_closures();
+        emitter.declare_temporaries();
 
-                if let Expr::Access {
-                    kind: AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin),
-                    place,
-                } = expr
-                {
-                    // TODO: handle fields and loans taken on subsets of their paths.
-                    // Until then: only support borrowing from complete places.
-                    //
-                    // TODO: we probably also need to track the loan's mode, if we want to emit
-                    // errors when mutably borrowing through a shared ref and the likes ?
-                    loans
-                        .entry(place.clone())
-                        .or_default()
-                        .push((origin.into(), (block_idx, statement_idx).into()));
+        // Collect loans from borrow expressions present in the program
+        let mut loans: HashMap<Place, Vec<(Origin, Location, LoanMode)>> = HashMap::new();
+        for (block_idx, bb) in emitter.body.basic_blocks.iter().enumerate() {
+            // Call terminators are evaluated after the block's statements.
+            let call = match &bb.terminator {
+                Terminator::Call { call, .. } => Some(call),
+                _ => None,
+            };
+            for (statement_idx, s) in bb.statements.iter().chain(call).enumerate() {
+                if let Statement::Assign(_, expr) | Statement::Expr(expr) = &**s {
+                    let location = (block_idx, statement_idx);
+                    emitter.collect_loans(expr, location, &mut loans);
                 }
             }
         }
+        emitter.loans = loans;
+        emitter.moved_out = emitter.collect_moved_out_variables();
+        emitter
+    }
 
-        Self {
-            input,
-            program,
-            loans,
-            simple_node_names,
+    // Collect the loans issued by the borrow expressions in `expr`, including the ones nested
+    // in call arguments.
+    fn collect_loans(
+        &self,
+        expr: &Expr,
+        location: (usize, usize),
+        loans: &mut HashMap<Place, Vec<(Origin, Location, LoanMode)>>,
+    ) {
+        match expr {
+            Expr::Access {
+                kind: kind @ (AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin)),
+                place,
+            } => {
+                // TODO: handle fields and loans taken on subsets of their paths.
+                // Until then: only support borrowing from complete places.
+                let mode = match kind {
+                    AccessKind::BorrowMut(_) => LoanMode::Mutable,
+                    _ => LoanMode::Shared,
+                };
+                loans.entry(place.clone()).or_default().push((
+                    origin.into(),
+                    location.into(),
+                    mode,
+                ));
+            }
+
+            Expr::Call { arguments, .. } => {
+                for expr in arguments {
+                    self.collect_loans(expr, location, loans);
+                }
+            }
+
+            Expr::BorrowRvalue {
+                kind,
+                rvalue,
+                temporary,
+                ..
+            } => {
+                self.collect_loans(rvalue, location, loans);
+                self.collect_loans(&temporary_borrow(kind, temporary), location, loans);
+            }
+
+            Expr::Struct { fields, .. } => {
+                for (_, expr) in fields {
+                    self.collect_loans(expr, location, loans);
+                }
+            }
+
+            // Loans cast to raw pointers are not tracked after the cast: uses of raw pointers are
+            // unchecked.
+            Expr::Cast { .. } => {}
+
+            _ => {}
         }
     }
 
     fn emit_facts(&self, facts: &mut Facts) {
-        for bb in &self.program.basic_blocks {
+        self.emit_implied_bounds(facts);
+
+        for bb in &self.body.basic_blocks {
             self.emit_block_facts(bb, facts);
         }
+
+        self.emit_loan_facts(facts);
+    }
+
+    // Emit the origin of each loan, and its metadata: the place it borrows and its mode, at the
+    // node where it's issued. These are ordered by node, and by origin within a node.
+    fn emit_loan_facts(&self, facts: &mut Facts) {
+        let mut loans: Vec<_> = self
+            .loans
+            .iter()
+            .flat_map(|(place, loans)| loans.iter().map(move |loan| (place, loan)))
+            .collect();
+        loans.sort_by_key(|(_, (origin, location, _))| {
+            (location.block_idx, location.statement_idx, origin.0.clone())
+        });
+
+        for (place, (origin, location, mode)) in loans {
+            let bb = &self.body.basic_blocks[location.block_idx];
+            let node = self.node_at(&bb.name, location.statement_idx);
+            facts.mark_as_loan_origin.push(origin.clone());
+            facts
+                .loan_issued_at
+                .push((origin.clone(), place.to_string(), *mode, node));
+        }
+    }
+
+    // Emit the implied bounds of the variables' types: the subsets required for these types
+    // to be well-formed, and which hold everywhere.
+    fn emit_implied_bounds(&self, facts: &mut Facts) {
+        for v in &self.body.variables {
+            let mut visiting = Vec::new();
+            self.collect_implied_bounds(&v.ty, &mut facts.known_subset, &mut visiting);
+        }
+    }
+
+    // Collects the outlives bounds implied by `ty` being well-formed: a reference `&'a T` is only
+    // well-formed if all the origins in `T` outlive `'a`, and a struct is only well-formed if its
+    // fields are, and if its declared `T: 'a` bounds hold.
+    fn collect_implied_bounds(
+        &self,
+        ty: &Ty,
+        bounds: &mut Vec<(Origin, Origin)>,
+        visiting: &mut Vec<Ty>,
+    ) {
+        match ty {
+            Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
+                let mut origins = Vec::new();
+                ty.collect_origins_into(&mut origins);
+                for nested_origin in origins {
+                    let bound = (nested_origin, origin.into());
+                    if bound.0 != bound.1 && !bounds.contains(&bound) {
+                        bounds.push(bound);
+                    }
+                }
+
+                self.collect_implied_bounds(ty, bounds, visiting);
+            }
+
+            Ty::Struct { name, parameters } => {
+                // Recursive structs: the fields' bounds are already being collected higher up in
+                // the stack.
+                if visiting.contains(ty) {
+                    return;
+                }
+
+                for param in parameters {
+                    if let Parameter::Ty(ty) = param {
+                        self.collect_implied_bounds(ty, bounds, visiting);
+                    }
+                }
+
+                if let Some(decl) = self.struct_decl(name) {
+                    visiting.push(ty.clone());
+                    let subst = Substitution::new(&decl.generic_decls, parameters);
+                    collect_declared_bounds(&decl.generic_decls, &subst, bounds);
+                    for field in &decl.field_decls {
+                        let field_ty = subst.apply_to_ty(&field.ty);
+                        self.collect_implied_bounds(&field_ty, bounds, visiting);
+                    }
+                    visiting.pop();
+                }
+            }
+
+            // Function pointer types don't imply bounds: their arguments are only required to be
+            // well-formed when they are called. Trait objects' erased types are unknown, and raw
+            // pointers are unchecked.
+            Ty::FnPtr { .. }
+            | Ty::Dyn { .. }
+            | Ty::RawPtr { .. }
+            | Ty::RawPtrMut { .. }
+            | Ty::Param { .. }
+            | Ty::I32
+            | Ty::Bool
+            | Ty::Unit => {}
+        }
     }
 
     fn emit_block_facts(&self, bb: &BasicBlock, facts: &mut Facts) {
         // Emit CFG facts for the block
         self.emit_cfg_edges(&bb, facts);
 
+        if bb.is_cleanup {
+            for idx in 0..Self::node_count(bb) {
+                facts.cleanup_node.push(self.node_at(&bb.name, idx));
+            }
+        }
+
         for (idx, s) in bb.statements.iter().enumerate() {
             let node = self.node_at(&bb.name, idx);
 
             // Emit `node_text` for this statement: the line from where it was parsed
-            // in the original input program.
-            let statement_text = {
-                let span = s.span();
-                self.input[span.start()..span.end() - 1].to_string()
+            // in the original input program, or the `StorageDead` of a temporary, which was
+            // introduced by the emitter.
+            let statement_text = match &**s {
+                Statement::StorageDead(temporary) if s.span().is_empty() => {
+                    format!("StorageDead({})", temporary)
+                }
+                _ => {
+                    let span = s.span();
+                    let text = &self.input[span.start()..span.end()];
+                    text.strip_suffix(';').unwrap_or(text).to_string()
+                }
             };
             facts.node_text.push((statement_text, node.clone()));
 
-            match &**s {
-                Statement::Assign(place, expr) => {
-                    // Emit facts about the assignment LHS
-                    let lhs_ty = self.ty_of_place(place);
-                    let lhs_origins = self.origins_of_place(place);
+            self.emit_statement_facts(&node, s, facts);
+        }
+
+        match &bb.terminator {
+            Terminator::Return => self.emit_return_facts(bb, facts),
 
-                    // Assignments clear all origins in the type
-                    for origin in &lhs_origins {
-                        facts.clear_origin.push((origin.clone(), node.clone()));
-                    }
+            Terminator::Call { call, .. } => self.emit_call_terminator_facts(bb, call, facts),
 
-                    // TODO: the following is wrong and simplistic, see 
-                    // https://github.com/nikomatsakis/polonius.next/pull/4#discussion_r739325010
-                    // but will be fixed by https://github.com/nikomatsakis/polonius.next/pull/10
-                    if !lhs_ty.is_ref() {
-                        // Assignments to non-references invalidate loans borrowing from them.
-                        //
-                        // TODO: handle assignments to fields and loans taken on subsets of
-                        // their paths. Until then: only support invalidations on assignments
-                        // to complete places.
-                        //
-                        if let Some(loans) = self.loans.get(place) {
-                            for (origin, _location) in loans {
-                                // TODO: if the `location` where the loan was issued can't
-                                // reach the current location, there is no need to emit
-                                // the invalidation
-                                facts.invalidate_origin.push((origin.clone(), node.clone()));
-                            }
-                        }
-                    }
+            Terminator::Goto(successors) if successors.len() > 1 => self.check_condition(bb),
 
-                    // Emit facts about the assignment RHS: evaluate the `expr`
-                    self.emit_expr_facts(&node, expr, facts);
+            Terminator::Goto(_) => {}
+        }
+    }
 
-                    // Relate the LHS and RHS tys
-                    self.emit_subset_facts(&node, &lhs_ty, expr, facts);
-                }
+    // Checks that the condition a block branches on is a `bool`, when its type is known. Like the
+    // `if`s and `while`s of the structured syntax are lowered, the condition is the expression
+    // statement evaluated last, before the `goto` to several successors: only the scopes of its
+    // temporaries end in between.
+    fn check_condition(&self, bb: &BasicBlock) {
+        let idx = bb
+            .statements
+            .iter()
+            .rposition(|s| !matches!(&**s, Statement::StorageDead(_) if s.span().is_empty()));
+        let (idx, cond) = match idx.map(|idx| (idx, &*bb.statements[idx])) {
+            Some((idx, Statement::Expr(cond))) => (idx, cond),
+            _ => return,
+        };
+
+        let node = self.node_at(&bb.name, idx);
+        let ty = self.ty_of_expr(&node, cond);
+        assert!(
+            matches!(ty, None | Some(Ty::Bool)),
+            "Can't branch on {:?}, whose type {:?} is not a bool",
+            cond,
+            ty,
+        );
+    }
+
+    fn emit_statement_facts(&self, node: &Node, statement: &Statement, facts: &mut Facts) {
+        match statement {
+            Statement::Assign(place, expr) => {
+                // Emit facts about the assignment LHS
+                self.emit_write_facts(node, place, facts);
+
+                // Emit facts about the assignment RHS: evaluate the `expr`
+                self.emit_expr_facts(node, expr, facts);
+
+                // Relate the LHS and RHS tys
+                let lhs_ty = self.ty_of_place(place);
+                self.emit_subset_facts(node, &lhs_ty, expr, facts);
+            }
+
+            Statement::Expr(expr) => {
+                // Evaluate the `expr`
+                self.emit_expr_facts(node, expr, facts);
+            }
+
+            Statement::Drop(place) => self.emit_drop_facts(node, place, facts),
+
+            Statement::StorageDead(variable) => self.emit_storage_dead_facts(node, variable, facts),
+        }
+    }
+
+    // Emit the facts of overwriting the value in `place`, before the assigned value is stored.
+    fn emit_write_facts(&self, node: &Node, place: &Place, facts: &mut Facts) {
+        assert!(
+            !self.is_static(place),
+            "Can't assign to static {}",
+            place.base
+        );
+
+        let lhs_origins = self.overwritten_origins(place);
 
-                Statement::Expr(expr) => {
-                    // Evaluate the `expr`
-                    self.emit_expr_facts(&node, expr, facts);
+        // Writing through derefs uses the references reaching the place: their origins
+        // are accessed, but they are not overwritten.
+        for origin in self.deref_origins(place) {
+            facts.access_origin.push((origin, node.clone()));
+        }
+
+        // Assignments clear all the overwritten origins, except the universal origins
+        // which are never cleared. Origins can appear multiple times in a type, but
+        // are only cleared once.
+        for (idx, origin) in lhs_origins.iter().enumerate() {
+            if !self.is_universal_origin(origin) && !lhs_origins[..idx].contains(origin) {
+                facts.clear_origin.push((origin.clone(), node.clone()));
+            }
+        }
+
+        // Assignments affect the loans of the place, and of its sub-places:
+        // - when the loan's place is reached through a deref of the assigned place, its
+        //   memory is not overwritten: the reference is only re-pointed, and the loan is
+        //   cleared, e.g. the loan of `*temp` when assigning to `temp: &T`.
+        // - otherwise, the loan's memory is overwritten, which invalidates the loan.
+        //
+        // The loans of the place's ancestors are also invalidated, as a part of their memory is
+        // overwritten, e.g. the loan of `s` when assigning to `s.f`, or of `p` when assigning to
+        // `*p`.
+        //
+        // TODO: if the `location` where the loan was issued can't reach the current
+        // location, there is no need to emit the invalidation
+        for (origin, loan_place) in self.loans_of_place_and_subplaces(place) {
+            let projections = &loan_place.projections[place.projections.len()..];
+            if projections.contains(&Projection::Deref) {
+                if !lhs_origins.contains(&origin) {
+                    facts.clear_origin.push((origin, node.clone()));
                 }
+            } else {
+                facts.invalidate_origin.push((origin, node.clone()));
+            }
+        }
+        for origin in self.loans_of_ancestors(place) {
+            facts.invalidate_origin.push((origin, node.clone()));
+        }
+    }
+
+    // Emit the facts of a call terminator. The call is evaluated at the terminator's own node,
+    // whether it returns or unwinds, but its destination is only written when it returns: at the
+    // next node, on the edge to the `return` target. There, the returned value is moved into the
+    // destination, accessing the origins of the instantiated return type.
+    fn emit_call_terminator_facts(&self, bb: &BasicBlock, call: &Sp<Statement>, facts: &mut Facts) {
+        let call_node = self.node_at(&bb.name, bb.statements.len());
+        let return_node = self.node_at(&bb.name, bb.statements.len() + 1);
+        let call_text = {
+            let span = call.span();
+            self.input[span.start()..span.end()].to_string()
+        };
+        facts
+            .node_text
+            .push((format!("{} -> return", call_text), return_node.clone()));
+        facts.node_text.push((call_text, call_node.clone()));
+
+        let (place, name, arguments) = match &**call {
+            Statement::Assign(place, Expr::Call { name, arguments }) => (place, name, arguments),
+            _ => return self.emit_statement_facts(&call_node, call, facts),
+        };
+        let expr = Expr::Call {
+            name: name.clone(),
+            arguments: arguments.clone(),
+        };
+        self.emit_expr_facts(&call_node, &expr, facts);
+
+        self.emit_write_facts(&return_node, place, facts);
+        if let Some(call) = self.instantiate_call(&call_node, name, arguments) {
+            let mut origins = Vec::new();
+            call.ret_ty.collect_origins_into(&mut origins);
+            for origin in origins {
+                facts.access_origin.push((origin, return_node.clone()));
+            }
+
+            let lhs_ty = self.ty_of_place(place);
+            self.relate_tys(
+                &return_node,
+                &lhs_ty,
+                &call.ret_ty,
+                Variance::Covariant,
+                facts,
+            );
+        }
+    }
+
+    // Emit the facts of a `return` terminator, at its own node:
+    // - the locals are dropped at the end of their scope, running their destructors
+    // - the locals whose scope didn't already end are dead when returning, which invalidates the
+    // loans of their memory
+    // - the return place is then read by the caller, at the exit node: the origins in the return
+    // type are live there.
+    fn emit_return_facts(&self, bb: &BasicBlock, facts: &mut Facts) {
+        let node = self.node_at(&bb.name, bb.statements.len());
+        facts.node_text.push(("return".to_string(), node.clone()));
+
+        for variable in self.implicitly_dropped_variables(&node) {
+            let place = Place {
+                base: variable.clone(),
+                projections: vec![],
+            };
+            self.emit_drop_access_facts(&node, &place, facts);
+        }
+
+        // Loans of places behind a deref don't borrow from the locals' memory, and the memory of
+        // statics is never freed. The value in the return place is moved out to the caller.
+        let storage_dead = self.storage_dead_variables();
+        let mut local_loans: Vec<_> = self
+            .loans
+            .iter()
+            .filter(|(place, _)| {
+                place.base != RETURN_PLACE
+                    && !self.is_static(place)
+                    && !storage_dead.contains(&&place.base)
+                    && !place.projections.contains(&Projection::Deref)
+            })
+            .flat_map(|(_, loans)| loans.iter().map(|(origin, ..)| origin.clone()))
+            .collect();
+        local_loans.sort_by(|origin1, origin2| origin1.0.cmp(&origin2.0));
+        for origin in local_loans {
+            facts.invalidate_origin.push((origin, node.clone()));
+        }
+
+        let exit_node = self.exit_node();
+        if self.body.signature.is_some() {
+            let return_place = Place {
+                base: RETURN_PLACE.to_string(),
+                projections: vec![],
+            };
+            for origin in self.origins_of_place(&return_place) {
+                facts.access_origin.push((origin, exit_node.clone()));
             }
         }
     }
@@ -203,6 +580,12 @@ impl<'a> FactEmitter<'a> {
                         facts.clear_origin.push((origin.into(), node.clone()));
 
                         if matches!(kind, AccessKind::BorrowMut(_)) {
+                            assert!(
+                                !self.is_static(place),
+                                "Can't mutably borrow static {}",
+                                place.base
+                            );
+
                             // A mutable borrow is considered a write to the place:
                             //
                             // 1) it accesses the origins in the type
@@ -211,20 +594,19 @@ impl<'a> FactEmitter<'a> {
                                 facts.access_origin.push((origin.clone(), node.clone()));
                             }
 
-                            // 2) and invalidates existing loans of that place
-                            //
-                            // TODO: handle assignments to fields and loans taken on subsets of
-                            // their paths. Until then: only support invalidations on assignments
-                            // to complete places.
-                            //
-                            // TODO: here as well, there is a question of: can the loans we're
-                            // invalidating, reach the current node ?
-                            //
-                            if let Some(loans) = self.loans.get(place) {
-                                for (origin, _) in loans {
-                                    facts.invalidate_origin.push((origin.clone(), node.clone()));
+                            // 2) and invalidates the existing loans of that place, of its
+                            // sub-places, and of its ancestors, which all overlap the mutably
+                            // borrowed memory. The places behind a deref of a shared reference
+                            // can't be modified through the borrow, and their loans are not
+                            // invalidated.
+                            for (origin, loan_place) in self.loans_of_place_and_subplaces(place) {
+                                if !self.derefs_shared_ref_below(loan_place, place) {
+                                    facts.invalidate_origin.push((origin, node.clone()));
                                 }
                             }
+                            for origin in self.loans_of_ancestors(place) {
+                                facts.invalidate_origin.push((origin, node.clone()));
+                            }
                         }
                     }
 
@@ -243,14 +625,91 @@ impl<'a> FactEmitter<'a> {
                 }
             }
 
-            Expr::Call { arguments, .. } => {
+            Expr::Call { name, arguments } => {
+                // Calls through function pointers or closures read the callee, accessing its free
+                // origins
+                if self.body.variables.iter().any(|v| &v.name == name) {
+                    let callee = Place {
+                        base: name.clone(),
+                        projections: vec![],
+                    };
+                    for origin in self.origins_of_place(&callee) {
+                        facts.access_origin.push((origin, node.clone()));
+                    }
+                }
+
                 // Calls evaluate their arguments
                 arguments
                     .iter()
                     .for_each(|expr| self.emit_expr_facts(&node, expr, facts));
 
-                // TODO: Depending on the signature of the function, some subsets can be introduced
+                // Depending on the signature of the function, some subsets can be introduced
                 // between the arguments to the call
+                self.emit_call_facts(node, name, arguments, facts);
+            }
+
+            Expr::Cast { expr, ty } => {
+                // Raw pointers can only be created from references, or other raw pointers, of the
+                // same mutability or less.
+                let source_ty = self.ty_of_expr(node, expr);
+                let valid_cast = matches!(
+                    (&source_ty, ty),
+                    (
+                        Some(
+                            Ty::Ref { .. }
+                                | Ty::RefMut { .. }
+                                | Ty::RawPtr { .. }
+                                | Ty::RawPtrMut { .. }
+                        ),
+                        Ty::RawPtr { .. },
+                    ) | (
+                        Some(Ty::RefMut { .. } | Ty::RawPtrMut { .. }),
+                        Ty::RawPtrMut { .. }
+                    )
+                );
+                assert!(valid_cast, "Can't cast {:?} to {:?}", source_ty, ty);
+
+                self.emit_expr_facts(node, expr, facts);
+
+                // Casting a borrow accesses its loan once: the raw pointer doesn't carry it
+                // further.
+                if let Expr::Access {
+                    kind: AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin),
+                    ..
+                } = &**expr
+                {
+                    facts.access_origin.push((origin.into(), node.clone()));
+                }
+            }
+
+            Expr::BorrowRvalue {
+                kind,
+                rvalue,
+                temporary,
+                ..
+            } => {
+                // The rvalue is evaluated into its temporary, which is then borrowed
+                self.emit_expr_facts(node, rvalue, facts);
+                self.emit_expr_facts(node, &temporary_borrow(kind, temporary), facts);
+            }
+
+            Expr::Struct { fields, .. } => {
+                // Struct literals evaluate their fields' values
+                for (_, expr) in fields {
+                    self.emit_expr_facts(node, expr, facts);
+                }
+            }
+
+            Expr::BinaryOp { op, lhs, rhs } => {
+                // Operators read their operands, which must have the types they apply to
+                self.ty_of_binary_op(node, *op, lhs, rhs);
+                self.emit_expr_facts(node, lhs, facts);
+                self.emit_expr_facts(node, rhs, facts);
+            }
+
+            Expr::UnaryOp { op, operand } => {
+                self.ty_of_unary_op(node, *op, operand);
+                self.emit_expr_facts(node, operand, facts);
             }
 
             _ => {}
@@ -274,10 +733,21 @@ impl<'a> FactEmitter<'a> {
         // In the context of an assignment, the subsets follow the flow of data, and origins on the
         // RHS will flow into the ones on the LHS.
         //
-        // We don't support function types in structs or function parameters at the moment, so
-        // there's no contravariant relationships yet.
+        // The arguments of function pointer types are contravariant: there, the subsets are
+        // reversed.
 
         match (lhs_ty, rhs_expr) {
+            // `lhs = &rvalue`: the temporary holding the rvalue is borrowed
+            (
+                _,
+                Expr::BorrowRvalue {
+                    kind, temporary, ..
+                },
+            ) => {
+                let borrow = temporary_borrow(kind, temporary);
+                self.emit_subset_facts(node, lhs_ty, &borrow, facts);
+            }
+
             // `lhs = &rhs`, where lhs is a shared reference type
             (
                 Ty::Ref {
@@ -295,7 +765,7 @@ impl<'a> FactEmitter<'a> {
                     node.clone(),
                 ));
                 let rhs_ty = self.ty_of_place(place);
-                self.relate_tys(node, lhs_ty, rhs_ty, Variance::Covariant, facts);
+                self.relate_tys(node, lhs_ty, &rhs_ty, Variance::Covariant, facts);
             }
 
             // `lhs = copy or move rhs`, where lhs and rhs are shared reference types
@@ -310,7 +780,7 @@ impl<'a> FactEmitter<'a> {
                 },
             ) => {
                 let rhs_ty = self.ty_of_place(place);
-                match rhs_ty {
+                match &rhs_ty {
                     Ty::Ref {
                         origin: source_origin,
                         ty: rhs_ty,
@@ -349,7 +819,8 @@ impl<'a> FactEmitter<'a> {
                     node.clone(),
                 ));
                 let rhs_ty = self.ty_of_place(place);
-                self.relate_tys(node, lhs_ty, rhs_ty, Variance::Invariant, facts);
+                let variance = unique_pointee_variance(lhs_ty, &rhs_ty, Variance::Covariant);
+                self.relate_tys(node, lhs_ty, &rhs_ty, variance, facts);
             }
 
             // `lhs = copy or move rhs`, where lhs and rhs are unique reference types
@@ -364,7 +835,7 @@ impl<'a> FactEmitter<'a> {
                 },
             ) => {
                 let rhs_ty = self.ty_of_place(place);
-                match rhs_ty {
+                match &rhs_ty {
                     Ty::RefMut {
                         origin: source_origin,
                         ty: rhs_ty,
@@ -374,7 +845,8 @@ impl<'a> FactEmitter<'a> {
                             target_origin.into(),
                             node.clone(),
                         ));
-                        self.relate_tys(node, lhs_ty, rhs_ty, Variance::Invariant, facts);
+                        let variance = unique_pointee_variance(lhs_ty, rhs_ty, Variance::Covariant);
+                        self.relate_tys(node, lhs_ty, rhs_ty, variance, facts);
                     }
 
                     _ => {
@@ -387,21 +859,99 @@ impl<'a> FactEmitter<'a> {
             }
 
             // `lhs = rhs`, where lhs and rhs are structs, and may have generic parameters which
-            // will need subsets.
+            // will need subsets; or are function pointers, whose signatures need to be related.
             (
-                Ty::Struct { .. },
+                Ty::Struct { .. } | Ty::FnPtr { .. },
                 Expr::Access {
                     kind: AccessKind::Copy | AccessKind::Move,
                     place,
                 },
             ) => {
                 let rhs_ty = self.ty_of_place(place);
-                self.relate_tys(node, lhs_ty, rhs_ty, Variance::Covariant, facts);
+                self.relate_tys(node, lhs_ty, &rhs_ty, Variance::Covariant, facts);
+            }
+
+            // `lhs = &rhs`, where lhs is a raw pointer: the reference must be explicitly cast
+            (
+                Ty::RawPtr { .. } | Ty::RawPtrMut { .. },
+                Expr::Access {
+                    kind: AccessKind::Borrow(_) | AccessKind::BorrowMut(_),
+                    ..
+                },
+            ) => {
+                panic!(
+                    "Can't assign {:?} to raw pointer {:?} without an explicit cast",
+                    rhs_expr, lhs_ty
+                );
             }
 
-            (_, Expr::Call { .. }) => {
-                // TODO: When possible, check if the function signature requires that the RHS inputs
-                // flow into the LHS output.
+            // `lhs = rhs as *const T`: raw pointers carry no origins, there are no subsets
+            (Ty::RawPtr { .. } | Ty::RawPtrMut { .. }, Expr::Cast { .. }) => {}
+
+            // `lhs = S { field: value }`: each value flows into the field of the LHS struct
+            (
+                Ty::Struct { name, parameters },
+                Expr::Struct {
+                    name: literal_name,
+                    fields,
+                },
+            ) => {
+                assert_eq!(
+                    name, literal_name,
+                    "Can't assign a {} struct literal to {:?}",
+                    literal_name, lhs_ty,
+                );
+                let decl = self
+                    .struct_decl(name)
+                    .unwrap_or_else(|| panic!("Can't find struct {}", name));
+                let mut field_names: Vec<_> = fields.iter().map(|(name, _)| name).collect();
+                let mut decl_field_names: Vec<_> =
+                    decl.field_decls.iter().map(|f| &f.name).collect();
+                field_names.sort();
+                decl_field_names.sort();
+                assert_eq!(
+                    field_names, decl_field_names,
+                    "The {} struct literal must have a value for each field",
+                    name,
+                );
+
+                // The fields' types can refer to the struct's generic parameters, which we need to
+                // substitute with the matching parameters from the LHS type.
+                let subst = Substitution::new(&decl.generic_decls, parameters);
+                for (field_name, expr) in fields {
+                    let field = decl
+                        .field_decls
+                        .iter()
+                        .find(|field| &field.name == field_name)
+                        .expect("The fields of the struct literal were checked");
+                    let field_ty = subst.apply_to_ty(&field.ty);
+                    self.emit_subset_facts(node, &field_ty, expr, facts);
+                }
+            }
+
+            (_, Expr::Struct { name, .. }) => {
+                panic!("Can't assign a {} struct literal to {:?}", name, lhs_ty);
+            }
+
+            (_, Expr::Call { name, arguments }) => {
+                // The function's instantiated return type flows into the LHS.
+                //
+                // TODO: calls to functions without a prototype can't be related to the LHS.
+                if let Some(call) = self.instantiate_call(node, name, arguments) {
+                    self.relate_tys(node, lhs_ty, &call.ret_ty, Variance::Covariant, facts);
+                }
+            }
+
+            // `lhs = a op b`: the results of operators have no origins, but must have the LHS type
+            (_, Expr::BinaryOp { .. } | Expr::UnaryOp { .. }) => {
+                let rhs_ty = self.ty_of_expr(node, rhs_expr);
+                assert_eq!(
+                    Some(lhs_ty),
+                    rhs_ty.as_ref(),
+                    "Can't assign {:?} to {:?}",
+                    rhs_expr,
+                    lhs_ty,
+                );
             }
 
             _ => {
@@ -413,7 +963,198 @@ impl<'a> FactEmitter<'a> {
         }
     }
 
-    // Emit subset relationships between the two types' parameters, according to the
+    // Emit the facts required by the signature of the called function, if it's known:
+    // - the generic origins are instantiated with fresh origins, which are cleared
+    // - the arguments flow into the instantiated parameter types
+    // - the generic types must outlive the origins they're declared to outlive
+    fn emit_call_facts(&self, node: &Node, name: &Name, arguments: &[Expr], facts: &mut Facts) {
+        let signature = match self.fn_signature(name) {
+            Some(signature) => signature,
+            None => return,
+        };
+
+        for generic_decl in &signature.generic_decls {
+            if let GenericDecl::Origin(origin) = generic_decl {
+                let fresh_origin = self.fresh_call_origin(node, name, origin);
+                facts.clear_origin.push((fresh_origin.into(), node.clone()));
+            }
+        }
+
+        let call = self
+            .instantiate_call(node, name, arguments)
+            .expect("Calls with a known signature can be instantiated");
+        for (arg_ty, expr) in call.arg_tys.iter().zip(arguments) {
+            if let Expr::Struct { .. } = expr {
+                // Struct literals flow into the parameter type field by field
+                self.emit_subset_facts(node, arg_ty, expr, facts);
+            } else if let Some(expr_ty) = self.ty_of_expr(node, expr) {
+                self.relate_tys(node, arg_ty, &expr_ty, Variance::Covariant, facts);
+            }
+        }
+
+        for (origin1, origin2) in call.bounds {
+            facts
+                .introduce_subset
+                .push((origin1, origin2, node.clone()));
+        }
+    }
+
+    // Returns the signature of the called function: either its prototype, or the type of the
+    // function pointer variable of the same name, whose bound origins are its generic origins.
+    // The signatures of closures are unknown: calling them only accesses their captures.
+    fn fn_signature(&self, name: &Name) -> Option<FnPrototype> {
+        if let Some(v) = self.body.variables.iter().find(|v| &v.name == name) {
+            let (binder, arg_tys, ret_ty) = v.ty.as_fn_ptr()?;
+            return Some(FnPrototype {
+                name: name.clone(),
+                generic_decls: binder.iter().cloned().map(GenericDecl::Origin).collect(),
+                arg_tys: arg_tys.to_vec(),
+                ret_ty: ret_ty.clone(),
+            });
+        }
+
+        self.fn_prototype(name).cloned()
+    }
+
+    // Instantiates the signature of the called function, if it's known, and returns its
+    // argument and return types, and the outlives bounds of its generic types:
+    // - the generic origins are substituted by fresh origins, specific to this call
+    // - the generic types are inferred from the types of the arguments
+    fn instantiate_call(
+        &self,
+        node: &Node,
+        name: &Name,
+        arguments: &[Expr],
+    ) -> Option<CallInstance> {
+        let signature = self.fn_signature(name)?;
+        assert_eq!(
+            signature.arg_tys.len(),
+            arguments.len(),
+            "Wrong number of arguments in call to {}",
+            name,
+        );
+
+        let mut subst = Substitution::default();
+        for generic_decl in &signature.generic_decls {
+            if let GenericDecl::Origin(origin) = generic_decl {
+                subst.bind_origin(origin, self.fresh_call_origin(node, name, origin));
+            }
+        }
+
+        for (arg_ty, expr) in signature.arg_tys.iter().zip(arguments) {
+            if let Some(expr_ty) = self.ty_of_expr(node, expr) {
+                subst.infer_tys(arg_ty, &expr_ty);
+            }
+        }
+
+        let arg_tys = signature
+            .arg_tys
+            .iter()
+            .map(|ty| subst.apply_to_ty(ty))
+            .collect();
+        let ret_ty = subst.apply_to_ty(&signature.ret_ty);
+        let mut bounds = Vec::new();
+        collect_declared_bounds(&signature.generic_decls, &subst, &mut bounds);
+        Some(CallInstance {
+            arg_tys,
+            ret_ty,
+            bounds,
+        })
+    }
+
+    // The name of the fresh origin instantiating the generic `origin` of the function `name`,
+    // when it's called at the given `node`.
+    fn fresh_call_origin(&self, node: &Node, name: &Name, origin: &Name) -> Name {
+        format!("'{}_{}_{}", name, origin.trim_start_matches('\''), node.0)
+    }
+
+    // Returns the type of the value produced by `expr`, if it's known: calls to functions without
+    // a prototype have no known type.
+    fn ty_of_expr(&self, node: &Node, expr: &Expr) -> Option<Ty> {
+        match expr {
+            Expr::Access { kind, place } => {
+                let ty = self.ty_of_place(place);
+                Some(match kind {
+                    AccessKind::Copy | AccessKind::Move => ty,
+                    AccessKind::Borrow(origin) => Ty::Ref {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    },
+                    AccessKind::BorrowMut(origin) => Ty::RefMut {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    },
+                })
+            }
+            Expr::Number { .. } => Some(Ty::I32),
+            Expr::Call { name, arguments } => self
+                .instantiate_call(node, name, arguments)
+                .map(|call| call.ret_ty),
+            Expr::Unit => Some(Ty::Unit),
+            Expr::Closure { .. } => unreachable!("Closures have been desugared"),
+            Expr::Cast { ty, .. } => Some(ty.clone()),
+            Expr::BorrowRvalue {
+                kind, temporary, ..
+            } => self.ty_of_expr(node, &temporary_borrow(kind, temporary)),
+            // The parameters of a struct literal's type are only known from the type it's
+            // assigned to.
+            Expr::Struct { .. } => None,
+            Expr::BinaryOp { op, lhs, rhs } => Some(self.ty_of_binary_op(node, *op, lhs, rhs)),
+            Expr::UnaryOp { op, operand } => Some(self.ty_of_unary_op(node, *op, operand)),
+        }
+    }
+
+    // Returns the type of the result of `lhs op rhs`, and checks the operands' types: arithmetic
+    // operators apply to `i32`s, comparisons to two `i32`s or two `bool`s, and logical operators
+    // to `bool`s.
+    fn ty_of_binary_op(&self, node: &Node, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Ty {
+        let lhs_ty = self.ty_of_expr(node, lhs);
+        let rhs_ty = self.ty_of_expr(node, rhs);
+        let ty = match (op, &lhs_ty, &rhs_ty) {
+            (
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem,
+                Some(Ty::I32),
+                Some(Ty::I32),
+            ) => Some(Ty::I32),
+            (
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge,
+                Some(Ty::I32),
+                Some(Ty::I32),
+            )
+            | (
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge
+                | BinaryOp::And
+                | BinaryOp::Or,
+                Some(Ty::Bool),
+                Some(Ty::Bool),
+            ) => Some(Ty::Bool),
+            _ => None,
+        };
+        ty.unwrap_or_else(|| panic!("Can't apply {:?} to {:?} and {:?}", op, lhs_ty, rhs_ty))
+    }
+
+    // Returns the type of the result of `op operand`, and checks the operand's type: `i32`s can
+    // be negated with both operators, and `bool`s with `!`.
+    fn ty_of_unary_op(&self, node: &Node, op: UnaryOp, operand: &Expr) -> Ty {
+        let operand_ty = self.ty_of_expr(node, operand);
+        match (op, operand_ty) {
+            (UnaryOp::Neg | UnaryOp::Not, Some(Ty::I32)) => Ty::I32,
+            (UnaryOp::Not, Some(Ty::Bool)) => Ty::Bool,
+            (_, operand_ty) => panic!("Can't apply {:?} to {:?}", op, operand_ty),
+        }
+    }
+
+    // Emit subset relationships between the two types' origins, according to the
     // variance rules, recursively.
     fn relate_tys(
         &self,
@@ -425,110 +1166,546 @@ impl<'a> FactEmitter<'a> {
     ) {
         match (lhs_ty, rhs_ty) {
             (
-                Ty::Struct {
-                    parameters: lhs_args,
-                    ..
+                Ty::Ref {
+                    origin: target_origin,
+                    ty: lhs_ty,
                 },
-                Ty::Struct {
-                    parameters: rhs_args,
-                    ..
+                Ty::Ref {
+                    origin: source_origin,
+                    ty: rhs_ty,
                 },
             ) => {
-                // Relate the arguments to the generic structs pair-wise, according to variance
-                for (lhs_arg, rhs_arg) in lhs_args.iter().zip(rhs_args.iter()) {
-                    match (lhs_arg, rhs_arg) {
-                        (
-                            Parameter::Ty(
-                                param @ Ty::Ref {
-                                    origin: target_origin,
-                                    ty: lhs_ty,
-                                },
-                            ),
-                            Parameter::Ty(Ty::Ref {
-                                origin: source_origin,
-                                ty: rhs_ty,
-                            }),
-                        )
-                        | (
-                            Parameter::Ty(
-                                param @ Ty::RefMut {
-                                    origin: target_origin,
-                                    ty: lhs_ty,
-                                },
-                            ),
-                            Parameter::Ty(Ty::RefMut {
-                                origin: source_origin,
-                                ty: rhs_ty,
-                            }),
-                        ) => {
-                            if let Variance::Covariant | Variance::Invariant = variance {
-                                facts.introduce_subset.push((
-                                    source_origin.into(),
-                                    target_origin.into(),
-                                    node.clone(),
-                                ));
-                            }
-
-                            if let Variance::Contravariant | Variance::Invariant = variance {
-                                facts.introduce_subset.push((
-                                    target_origin.into(),
-                                    source_origin.into(),
-                                    node.clone(),
-                                ));
-                            }
-
-                            // Unique references change the relationships of their children
-                            // parameter pairs: they must be invariant.
-                            let variance = if matches!(param, Ty::RefMut { .. }) {
-                                Variance::Invariant
-                            } else {
-                                variance
-                            };
-
-                            self.relate_tys(node, &lhs_ty, &rhs_ty, variance, facts);
-                        }
+                self.relate_origins(node, target_origin, source_origin, variance, facts);
+                self.relate_tys(node, lhs_ty, rhs_ty, variance, facts);
+            }
 
-                        (Parameter::Ty(lhs_ty), Parameter::Ty(rhs_ty)) => {
+            (
+                Ty::RefMut {
+                    origin: target_origin,
+                    ty: lhs_ty,
+                },
+                Ty::RefMut {
+                    origin: source_origin,
+                    ty: rhs_ty,
+                },
+            ) => {
+                self.relate_origins(node, target_origin, source_origin, variance, facts);
+
+                // Unique references change the relationships of their children
+                // parameter pairs: they must be invariant.
+                let variance = unique_pointee_variance(lhs_ty, rhs_ty, variance);
+                self.relate_tys(node, lhs_ty, rhs_ty, variance, facts);
+            }
+
+            (
+                Ty::Struct {
+                    name,
+                    parameters: lhs_args,
+                },
+                Ty::Struct {
+                    name: rhs_name,
+                    parameters: rhs_args,
+                },
+            ) => {
+                assert!(
+                    name == rhs_name && lhs_args.len() == rhs_args.len(),
+                    "Can't relate structs {:?} and {:?}",
+                    lhs_ty,
+                    rhs_ty,
+                );
+
+                // Relate the arguments to the generic structs pair-wise, according to the
+                // variance of the struct's generic parameters in the current context.
+                for (idx, (lhs_arg, rhs_arg)) in lhs_args.iter().zip(rhs_args.iter()).enumerate() {
+                    let variance = variance.xform(self.variance_of_generic(name, idx));
+                    match (lhs_arg, rhs_arg) {
+                        (Parameter::Origin(target_origin), Parameter::Origin(source_origin)) => {
+                            self.relate_origins(
+                                node,
+                                target_origin,
+                                source_origin,
+                                variance,
+                                facts,
+                            );
+                        }
+
+                        (Parameter::Ty(lhs_ty), Parameter::Ty(rhs_ty)) => {
                             // TODO: variance can also change if the type is special here:
                             // e.g. UnsafeCell
-                            self.relate_tys(node, &lhs_ty, &rhs_ty, variance, facts);
+                            self.relate_tys(node, lhs_ty, rhs_ty, variance, facts);
                         }
 
-                        _ => todo!(),
+                        _ => panic!(
+                            "Can't relate parameters {:?} and {:?} of struct {}",
+                            lhs_arg, rhs_arg, name
+                        ),
                     }
                 }
             }
 
+            (Ty::FnPtr { .. }, Ty::FnPtr { .. }) => {
+                if let Variance::Covariant | Variance::Invariant = variance {
+                    self.relate_fn_ptr_tys(node, lhs_ty, rhs_ty, facts);
+                }
+
+                if let Variance::Contravariant | Variance::Invariant = variance {
+                    self.relate_fn_ptr_tys(node, rhs_ty, lhs_ty, facts);
+                }
+            }
+
+            (
+                Ty::Dyn {
+                    name: lhs_name,
+                    origin: target_origin,
+                },
+                Ty::Dyn {
+                    name: rhs_name,
+                    origin: source_origin,
+                },
+            ) => {
+                assert_eq!(
+                    lhs_name, rhs_name,
+                    "Can't relate trait objects {:?} and {:?}",
+                    lhs_ty, rhs_ty
+                );
+                self.relate_origins(node, target_origin, source_origin, variance, facts);
+            }
+
+            // Unsizing coercion of a concrete type into a trait object: the origins of the
+            // concrete type are erased, and must outlive the trait object's bound. Types can only
+            // be coerced in covariant positions: elsewhere, e.g. behind two unique references,
+            // the concrete type would be observable through the other reference.
+            (
+                Ty::Dyn {
+                    origin: target_origin,
+                    ..
+                },
+                _,
+            ) => {
+                assert_eq!(
+                    variance,
+                    Variance::Covariant,
+                    "Can't coerce {:?} into trait object {:?}: the position is {:?}, not covariant",
+                    rhs_ty,
+                    lhs_ty,
+                    variance,
+                );
+
+                let mut source_origins = Vec::new();
+                rhs_ty.collect_origins_into(&mut source_origins);
+                for source_origin in source_origins {
+                    self.relate_origins(
+                        node,
+                        target_origin,
+                        &source_origin.0,
+                        Variance::Covariant,
+                        facts,
+                    );
+                }
+            }
+
             _ => {}
         }
     }
 
+    // Emit the subsets required for a function pointer of type `source_ty` to flow into a
+    // function pointer of type `target_ty`. They are contravariant in their arguments, and
+    // covariant in their return type, and their binders are instantiated before relating them:
+    // - the origins bound in the source are instantiated with fresh origins, which are cleared:
+    // that function can be used with any origins.
+    // - the origins bound in the target are instantiated with placeholders, which can only be
+    // related to the source's fresh origins: that function must be usable with all origins.
+    // Otherwise, one type is more general than the other and they can't be related.
+    fn relate_fn_ptr_tys(&self, node: &Node, target_ty: &Ty, source_ty: &Ty, facts: &mut Facts) {
+        let (target_binder, target_arg_tys, target_ret_ty) = target_ty.as_fn_ptr().unwrap();
+        let (source_binder, source_arg_tys, source_ret_ty) = source_ty.as_fn_ptr().unwrap();
+        assert_eq!(
+            target_arg_tys.len(),
+            source_arg_tys.len(),
+            "Mismatched function pointer types: expected {:?}, found {:?}",
+            target_ty,
+            source_ty,
+        );
+
+        let mut target_subst = Substitution::default();
+        let mut placeholders = Vec::new();
+        for origin in target_binder {
+            let placeholder = format!(
+                "'{}_placeholder_{}",
+                origin.trim_start_matches('\''),
+                node.0
+            );
+            target_subst.bind_origin(origin, placeholder.clone());
+            placeholders.push(Origin::from(placeholder));
+        }
+
+        let mut source_subst = Substitution::default();
+        let mut fresh_origins = Vec::new();
+        for origin in source_binder {
+            let fresh_origin = format!("'{}_{}", origin.trim_start_matches('\''), node.0);
+            source_subst.bind_origin(origin, fresh_origin.clone());
+            facts
+                .clear_origin
+                .push((fresh_origin.clone().into(), node.clone()));
+            fresh_origins.push(Origin::from(fresh_origin));
+        }
+
+        // Relate the instantiated signatures, separately from the other facts: subsets
+        // involving placeholders need to be checked, and are not emitted.
+        let mut fn_facts = Facts::default();
+        for (target_arg_ty, source_arg_ty) in target_arg_tys.iter().zip(source_arg_tys) {
+            self.relate_tys(
+                node,
+                &target_subst.apply_to_ty(target_arg_ty),
+                &source_subst.apply_to_ty(source_arg_ty),
+                Variance::Contravariant,
+                &mut fn_facts,
+            );
+        }
+        self.relate_tys(
+            node,
+            &target_subst.apply_to_ty(target_ret_ty),
+            &source_subst.apply_to_ty(source_ret_ty),
+            Variance::Covariant,
+            &mut fn_facts,
+        );
+
+        // Check that the placeholders are only related to the source's fresh origins, directly
+        // or transitively. The only other origin that can flow into them is `'static`.
+        let subsets: Vec<_> = fn_facts
+            .introduce_subset
+            .iter()
+            .map(|(origin1, origin2, _)| (origin1.clone(), origin2.clone()))
+            .collect();
+        for placeholder in &placeholders {
+            let successors = reachable_origins(&subsets, placeholder, false);
+            let predecessors = reachable_origins(&subsets, placeholder, true)
+                .into_iter()
+                .filter(|origin| origin.0 != STATIC_ORIGIN);
+            for origin in successors.into_iter().chain(predecessors) {
+                if origin != *placeholder && !fresh_origins.contains(&origin) {
+                    panic!(
+                        "Mismatched function pointer types: expected {:?}, found {:?}, \
+                        one type is more general than the other",
+                        target_ty, source_ty,
+                    );
+                }
+            }
+        }
+
+        facts.clear_origin.extend(fn_facts.clear_origin);
+        facts
+            .introduce_subset
+            .extend(
+                fn_facts
+                    .introduce_subset
+                    .into_iter()
+                    .filter(|(origin1, origin2, _)| {
+                        !placeholders.contains(origin1) && !placeholders.contains(origin2)
+                    }),
+            );
+    }
+
+    // Emit the subsets between a pair of origins, with the `source_origin` flowing into the
+    // `target_origin` when covariant, the opposite when contravariant, and both ways when
+    // invariant.
+    fn relate_origins(
+        &self,
+        node: &Node,
+        target_origin: &Name,
+        source_origin: &Name,
+        variance: Variance,
+        facts: &mut Facts,
+    ) {
+        // An origin trivially outlives itself
+        if target_origin == source_origin {
+            return;
+        }
+
+        if let Variance::Covariant | Variance::Invariant = variance {
+            facts
+                .introduce_subset
+                .push((source_origin.into(), target_origin.into(), node.clone()));
+        }
+
+        if let Variance::Contravariant | Variance::Invariant = variance {
+            facts
+                .introduce_subset
+                .push((target_origin.into(), source_origin.into(), node.clone()));
+        }
+    }
+
+    // Computes the variance of the struct's generic parameter at `idx`, from the way it's used
+    // in the struct's fields. Structs without a declaration are assumed to be covariant in all
+    // their parameters.
+    fn variance_of_generic(&self, struct_name: &str, idx: usize) -> Variance {
+        let mut visiting = Vec::new();
+        self.variance_of_generic_in(struct_name, idx, &mut visiting)
+    }
+
+    fn variance_of_generic_in(
+        &self,
+        struct_name: &str,
+        idx: usize,
+        visiting: &mut Vec<Name>,
+    ) -> Variance {
+        let decl = match self.struct_decl(struct_name) {
+            Some(decl) => decl,
+            None => return Variance::Covariant,
+        };
+
+        // Recursive structs: the parameters' uses are already being computed higher up
+        // in the stack.
+        if visiting.iter().any(|name| name == struct_name) {
+            return Variance::Covariant;
+        }
+
+        let generic_name = match &decl.generic_decls[idx] {
+            GenericDecl::Origin(name) | GenericDecl::Ty { name, .. } => name,
+        };
+
+        visiting.push(struct_name.to_string());
+        let mut variance = None;
+        for field in &decl.field_decls {
+            self.collect_variance_of_generic(
+                &field.ty,
+                generic_name,
+                Variance::Covariant,
+                &mut variance,
+                visiting,
+            );
+        }
+        visiting.pop();
+
+        // Unused parameters are bivariant, which we don't need to distinguish from covariance
+        // when relating types.
+        variance.unwrap_or(Variance::Covariant)
+    }
+
+    // Joins the variances of all the uses of the generic named `generic_name` in `ty`, where `ty`
+    // itself is in a context of the `ambient` variance.
+    fn collect_variance_of_generic(
+        &self,
+        ty: &Ty,
+        generic_name: &Name,
+        ambient: Variance,
+        variance: &mut Option<Variance>,
+        visiting: &mut Vec<Name>,
+    ) {
+        match ty {
+            Ty::Ref { origin, ty } => {
+                if origin == generic_name {
+                    Variance::join_into(variance, ambient);
+                }
+                self.collect_variance_of_generic(ty, generic_name, ambient, variance, visiting);
+            }
+
+            Ty::RefMut { origin, ty } => {
+                if origin == generic_name {
+                    Variance::join_into(variance, ambient);
+                }
+                let ambient = ambient.xform(Variance::Invariant);
+                self.collect_variance_of_generic(ty, generic_name, ambient, variance, visiting);
+            }
+
+            Ty::RawPtr { ty } => {
+                self.collect_variance_of_generic(ty, generic_name, ambient, variance, visiting);
+            }
+
+            Ty::RawPtrMut { ty } => {
+                let ambient = ambient.xform(Variance::Invariant);
+                self.collect_variance_of_generic(ty, generic_name, ambient, variance, visiting);
+            }
+
+            Ty::Param { name } | Ty::Dyn { origin: name, .. } => {
+                if name == generic_name {
+                    Variance::join_into(variance, ambient);
+                }
+            }
+
+            Ty::Struct { name, parameters } => {
+                for (idx, param) in parameters.iter().enumerate() {
+                    let ambient = ambient.xform(self.variance_of_generic_in(name, idx, visiting));
+                    match param {
+                        Parameter::Origin(origin) => {
+                            if origin == generic_name {
+                                Variance::join_into(variance, ambient);
+                            }
+                        }
+                        Parameter::Ty(ty) => {
+                            self.collect_variance_of_generic(
+                                ty,
+                                generic_name,
+                                ambient,
+                                variance,
+                                visiting,
+                            );
+                        }
+                    }
+                }
+            }
+
+            Ty::FnPtr {
+                binder,
+                arg_tys,
+                ret_ty,
+            } => {
+                // The origins bound by the binder shadow the generic origins
+                if binder.contains(generic_name) {
+                    return;
+                }
+
+                // Function pointers are contravariant in their arguments
+                let arg_ambient = ambient.xform(Variance::Contravariant);
+                for ty in arg_tys {
+                    self.collect_variance_of_generic(
+                        ty,
+                        generic_name,
+                        arg_ambient,
+                        variance,
+                        visiting,
+                    );
+                }
+                self.collect_variance_of_generic(ret_ty, generic_name, ambient, variance, visiting);
+            }
+
+            Ty::I32 | Ty::Bool | Ty::Unit => {}
+        }
+    }
+
     fn emit_cfg_edges(&self, bb: &BasicBlock, facts: &mut Facts) {
-        let statement_count = bb.statements.len();
+        let node_count = Self::node_count(bb);
 
-        // Emit intra-block CFG edges between statements
-        for idx in 1..statement_count {
+        // Emit intra-block CFG edges between statements, and the terminator's node
+        for idx in 1..node_count {
             facts
                 .cfg_edge
                 .push((self.node_at(&bb.name, idx - 1), self.node_at(&bb.name, idx)));
         }
 
-        // Emit inter-block CFG edges between a block and its successors
-        for succ in &bb.successors {
+        // Emit inter-block CFG edges between a block and its successors. Cleanup blocks only run
+        // while unwinding: they are reached from a call's `unwind` edge, or from other cleanup
+        // blocks, and can't return.
+        assert!(
+            !(bb.is_cleanup && matches!(bb.terminator, Terminator::Return)),
+            "Cleanup block {} can't return",
+            bb.name,
+        );
+        for succ in bb.successors() {
+            let unwinds = match &bb.terminator {
+                Terminator::Call { unwind, .. } => unwind.as_ref() == Some(succ),
+                _ => false,
+            };
+            let succ_is_cleanup = self
+                .body
+                .basic_blocks
+                .iter()
+                .find(|candidate| &candidate.name == succ)
+                .unwrap_or_else(|| panic!("Can't find block {}", succ))
+                .is_cleanup;
+            assert_eq!(
+                succ_is_cleanup,
+                bb.is_cleanup || unwinds,
+                "Invalid edge from block {} to block {}: only unwinding edges and cleanup blocks \
+                can continue to cleanup blocks",
+                bb.name,
+                succ,
+            );
+
             // Note: `goto`s are not statements, so a block with a single goto
-            // has no statements but still needs a node index in the CFG.
-            facts.cfg_edge.push((
-                self.node_at(&bb.name, statement_count.saturating_sub(1)),
-                self.node_at(succ, 0),
-            ));
+            // has no statements but still needs a node index in the CFG. Calls unwind from the
+            // call's node, before their destination is written.
+            let last_idx = if unwinds {
+                node_count - 2
+            } else {
+                node_count - 1
+            };
+            facts
+                .cfg_edge
+                .push((self.node_at(&bb.name, last_idx), self.node_at(succ, 0)));
+        }
+
+        // Returning exits the function
+        if let Terminator::Return = bb.terminator {
+            facts
+                .cfg_edge
+                .push((self.node_at(&bb.name, node_count - 1), self.exit_node()));
         }
     }
 
-    fn ty_of_place(&self, place: &Place) -> &Ty {
+    // The number of nodes in the block: one per statement, one for the `return` terminator, and
+    // two for call terminators: the call, and the write of its destination when it returns.
+    // There's always at least one node per block, to account for empty blocks with a `goto`.
+    fn node_count(bb: &BasicBlock) -> usize {
+        let terminator_count = match bb.terminator {
+            Terminator::Return => 1,
+            Terminator::Call { .. } => 2,
+            Terminator::Goto(_) => 0,
+        };
+        (bb.statements.len() + terminator_count).max(1)
+    }
+
+    fn struct_decl(&self, name: &str) -> Option<&StructDecl> {
+        self.program.struct_decls.iter().find(|s| s.name == name)
+    }
+
+    // Returns the prototype of the function named `name`: either a declared prototype, or the
+    // signature of one of the program's bodies.
+    fn fn_prototype(&self, name: &str) -> Option<&FnPrototype> {
+        let signatures = self
+            .program
+            .bodies
+            .iter()
+            .filter_map(|body| body.signature.as_ref());
+        self.program
+            .fn_prototypes
+            .iter()
+            .chain(signatures)
+            .find(|f| f.name == name)
+    }
+
+    // Returns the declared type of the variable, static or constant named `name`.
+    fn ty_of_base(&self, name: &Name) -> &Ty {
+        let variables = self.body.variables.iter().map(|v| (&v.name, &v.ty));
+        let statics = self.program.static_decls.iter().map(|s| (&s.name, &s.ty));
+        variables
+            .chain(statics)
+            .find_map(|(candidate, ty)| if candidate == name { Some(ty) } else { None })
+            .unwrap_or_else(|| panic!("Can't find variable {}", name))
+    }
+
+    // Returns true if the origin is valid for the entire function: `'static`, or one of the
+    // generic origins of the analyzed function's signature.
+    fn is_universal_origin(&self, origin: &Origin) -> bool {
+        if origin.0 == STATIC_ORIGIN {
+            return true;
+        }
+
+        self.body.signature.iter().any(|signature| {
+            signature
+                .generic_decls
+                .iter()
+                .any(|decl| matches!(decl, GenericDecl::Origin(name) if *name == origin.0))
+        })
+    }
+
+    fn is_static(&self, place: &Place) -> bool {
+        self.program
+            .static_decls
+            .iter()
+            .any(|s| s.name == place.base)
+    }
+
+    fn ty_of_place(&self, place: &Place) -> Ty {
         self.walk_place_tys(place, |_| ())
     }
 
     fn origins_of_place(&self, place: &Place) -> Vec<Origin> {
+        // Derefs through raw pointers are unchecked: they access no origins.
+        if self.derefs_raw_ptr(place) {
+            return Vec::new();
+        }
+
         let mut origins = Vec::new();
         self.walk_place_tys(place, |ty| {
             ty.collect_origins_into(&mut origins);
@@ -536,39 +1713,180 @@ impl<'a> FactEmitter<'a> {
         origins
     }
 
-    fn walk_place_tys<F>(&self, place: &Place, mut ty_walked_callback: F) -> &Ty
+    // Returns the origins of the references dereferenced to reach the place.
+    fn deref_origins(&self, place: &Place) -> Vec<Origin> {
+        let mut tys = Vec::new();
+        self.walk_place_tys(place, |ty| tys.push(ty.clone()));
+
+        let mut origins = Vec::new();
+        for (proj, ty) in place.projections.iter().zip(tys) {
+            if let (Projection::Deref, Ty::Ref { origin, .. } | Ty::RefMut { origin, .. }) =
+                (proj, ty)
+            {
+                origins.push(origin.into());
+            }
+        }
+        origins
+    }
+
+    // Returns the origins overwritten by an assignment to the place. Writing through a deref
+    // only overwrites the data behind the last deref: the references and their parents are not
+    // overwritten. Similarly, writing to a field only overwrites the origins in the field's type:
+    // the origins that the parent structs also use in their other fields are only partially
+    // overwritten, and are kept.
+    fn overwritten_origins(&self, place: &Place) -> Vec<Origin> {
+        // Writes through raw pointers are unchecked: they overwrite no origins.
+        if self.derefs_raw_ptr(place) {
+            return Vec::new();
+        }
+
+        let mut tys = Vec::new();
+        self.walk_place_tys(place, |ty| tys.push(ty.clone()));
+
+        // The types are walked before each projection, and then the place's type: the types
+        // after the last deref are the ones overwritten.
+        let overwritten_start = match place
+            .projections
+            .iter()
+            .rposition(|p| *p == Projection::Deref)
+        {
+            Some(idx) => idx + 1,
+            None => 0,
+        };
+        let mut kept_origins = Vec::new();
+        for (proj, ty) in place.projections.iter().zip(&tys).skip(overwritten_start) {
+            if let Projection::Field(field_name) = proj {
+                self.collect_sibling_field_origins(ty, field_name, &mut kept_origins);
+            }
+        }
+
+        let mut origins = Vec::new();
+        tys.last()
+            .expect("The place's type is always walked")
+            .collect_origins_into(&mut origins);
+        origins.retain(|origin| !kept_origins.contains(origin));
+        origins
+    }
+
+    // Collect the origins in the types of the fields of the struct `ty`, except `field_name`.
+    fn collect_sibling_field_origins(&self, ty: &Ty, field_name: &Name, origins: &mut Vec<Origin>) {
+        let (decl, parameters) = match ty {
+            Ty::Struct { name, parameters } => (
+                self.struct_decl(name)
+                    .expect("Fields are only found in declared structs"),
+                parameters,
+            ),
+            _ => panic!("Ty {:?} must be a struct to access its fields", ty),
+        };
+        let subst = Substitution::new(&decl.generic_decls, parameters);
+        for field in decl.field_decls.iter().filter(|f| &f.name != field_name) {
+            subst.apply_to_ty(&field.ty).collect_origins_into(origins);
+        }
+    }
+
+    // Returns the loans of the place, and of its sub-places, with the place they borrow, sorted by
+    // name.
+    fn loans_of_place_and_subplaces(&self, place: &Place) -> Vec<(Origin, &Place)> {
+        let mut loans: Vec<_> = self
+            .loans
+            .iter()
+            .filter(|(loan_place, _)| {
+                loan_place.base == place.base
+                    && loan_place.projections.starts_with(&place.projections)
+            })
+            .flat_map(|(loan_place, loans)| {
+                loans
+                    .iter()
+                    .map(move |(origin, ..)| (origin.clone(), loan_place))
+            })
+            .collect();
+        loans.sort_by(|(origin1, _), (origin2, _)| origin1.0.cmp(&origin2.0));
+        loans
+    }
+
+    // Returns the loans of the ancestors of the place, the places it's a part of, sorted by name.
+    // The ancestors of a deref of a shared reference are not modified through it, and are
+    // ignored.
+    fn loans_of_ancestors(&self, place: &Place) -> Vec<Origin> {
+        let mut ancestors = Vec::new();
+        for idx in (0..place.projections.len()).rev() {
+            let ancestor = Place {
+                base: place.base.clone(),
+                projections: place.projections[..idx].to_vec(),
+            };
+            if place.projections[idx] == Projection::Deref
+                && matches!(self.ty_of_place(&ancestor), Ty::Ref { .. })
+            {
+                break;
+            }
+            ancestors.push(ancestor);
+        }
+
+        let mut loans: Vec<_> = ancestors
+            .iter()
+            .filter_map(|ancestor| self.loans.get(ancestor))
+            .flatten()
+            .map(|(origin, ..)| origin.clone())
+            .collect();
+        loans.sort_by(|origin1, origin2| origin1.0.cmp(&origin2.0));
+        loans
+    }
+
+    // Returns true if one of the derefs in this place, below its `ancestor`, is a deref of a shared
+    // reference.
+    fn derefs_shared_ref_below(&self, place: &Place, ancestor: &Place) -> bool {
+        (ancestor.projections.len()..place.projections.len()).any(|idx| {
+            let prefix = Place {
+                base: place.base.clone(),
+                projections: place.projections[..idx].to_vec(),
+            };
+            place.projections[idx] == Projection::Deref
+                && matches!(self.ty_of_place(&prefix), Ty::Ref { .. })
+        })
+    }
+
+    // Returns true if one of the derefs in this place is a deref of a raw pointer.
+    fn derefs_raw_ptr(&self, place: &Place) -> bool {
+        place.projections.iter().enumerate().any(|(idx, proj)| {
+            if let Projection::Deref = proj {
+                let prefix = Place {
+                    base: place.base.clone(),
+                    projections: place.projections[..idx].to_vec(),
+                };
+                matches!(
+                    self.ty_of_place(&prefix),
+                    Ty::RawPtr { .. } | Ty::RawPtrMut { .. }
+                )
+            } else {
+                false
+            }
+        })
+    }
+
+    fn walk_place_tys<F>(&self, place: &Place, mut ty_walked_callback: F) -> Ty
     where
         F: FnMut(&Ty),
     {
-        let v = self
-            .program
-            .variables
-            .iter()
-            .find(|v| v.name == place.base)
-            .unwrap_or_else(|| panic!("Can't find variable {}", place.base));
-
-        let mut ty = &v.ty;
+        let mut ty = self.ty_of_base(&place.base).clone();
 
         for proj in &place.projections {
             // Notify a traversal step was taken for the current field parent's ty
-            ty_walked_callback(ty);
+            ty_walked_callback(&ty);
 
             match proj {
-                Projection::Deref => ty = ty.target().expect("Deref of non-reference type"),
+                Projection::Deref => {
+                    ty = ty.target().expect("Deref of non-reference type").clone();
+                }
 
                 Projection::Field(field_name) => {
-                    let (struct_name, struct_substs) = match ty {
+                    let (struct_name, struct_substs) = match &ty {
                         Ty::Struct { name, parameters } => (name, parameters),
+                        Ty::Dyn { .. } => panic!("Trait object {:?} has no known fields", ty),
                         _ => panic!("Ty {:?} must be a struct to access its fields", ty),
                     };
-                    let decl = self
-                        .program
-                        .struct_decls
-                        .iter()
-                        .find(|s| &s.name == struct_name)
-                        .unwrap_or_else(|| {
-                            panic!("Can't find struct {} at field {}", struct_name, field_name,)
-                        });
+                    let decl = self.struct_decl(struct_name).unwrap_or_else(|| {
+                        panic!("Can't find struct {} at field {}", struct_name, field_name,)
+                    });
 
                     // Find the expected named field inside the struct decl
                     let field = decl
@@ -579,32 +1897,10 @@ impl<'a> FactEmitter<'a> {
                             panic!("Can't find field {} in struct {}", field_name, struct_name)
                         });
 
-                    // It's possible that the field has a generic type, which we need to substitute
-                    // with the matching type from the struct's arguments
-                    ty = match &field.ty {
-                        Ty::Struct {
-                            name: field_ty_name,
-                            ..
-                        } => {
-                            if let Some(idx) = decl.generic_decls.iter().position(|d| match d {
-                                GenericDecl::Ty(param_ty_name) => param_ty_name == field_ty_name,
-                                _ => false,
-                            }) {
-                                // We found the field ty in the generic decls, so return the subst
-                                // at the same index
-                                match &struct_substs[idx] {
-                                    Parameter::Ty(subst_ty) => subst_ty,
-
-                                    // TODO: handle generic origins
-                                    _ => panic!("The parameter at idx {} should be a Ty", idx),
-                                }
-                            } else {
-                                // Otherwise, the field ty is a regular type
-                                &field.ty
-                            }
-                        }
-                        _ => &field.ty,
-                    }
+                    // The field's type can refer to the struct's generic parameters, which we need
+                    // to substitute with the matching parameters from the struct's type.
+                    let subst = Substitution::new(&decl.generic_decls, struct_substs);
+                    ty = subst.apply_to_ty(&field.ty);
                 }
             }
         }
@@ -613,41 +1909,57 @@ impl<'a> FactEmitter<'a> {
         // - the `base` ty, when there are no fields
         // - the last field's ty, from the place's `fields` list. The callbacks for the previous
         // fields in the list have already been processed in the loop just above.
-        ty_walked_callback(ty);
+        ty_walked_callback(&ty);
         ty
     }
 
     fn node_at(&self, block: &str, statement_idx: usize) -> Node {
-        let mut node = format!("{}[{}]", block, statement_idx);
+        let node = format!("{}[{}]", block, statement_idx);
 
-        // Hack: if we temporarily need simpler node names, while comparing to the manual facts:
-        // use single-letter names.
-        let use_simple_node_names = std::env::var("SIMPLE_NODES").is_ok();
-
-        if self.simple_node_names || use_simple_node_names {
+        if self.use_simple_node_names() {
             // Make the block-local statement idx refer to a concatenated list of all
-            // statements: adding the number of statements prior to this block.
-            // (Here as well, count as if there's always at least one statement per block,
-            // to account for empty blocks with a goto)
+            // statements: adding the number of nodes prior to this block.
             let bb_statement_start_idx = self
-                .program
+                .body
                 .basic_blocks
                 .iter()
                 .take_while(|bb| block != bb.name)
-                .fold(0, |acc, bb| acc + bb.statements.len().max(1));
-            let node_idx = 'a' as u32 + (bb_statement_start_idx + statement_idx) as u32;
-            let node_as_letter = char::from_u32(node_idx).unwrap_or_else(|| {
-                panic!(
-                    "Couldn't turn '{}' into a single letter name for node {:?}",
-                    node_idx, node
-                )
-            });
-            node = node_as_letter.to_string().into()
+                .fold(0, |acc, bb| acc + Self::node_count(bb));
+            return Self::simple_node_name(bb_statement_start_idx + statement_idx, &node);
         }
 
         node.into()
     }
 
+    // The node where the function exits after returning, following all the blocks' nodes.
+    fn exit_node(&self) -> Node {
+        let node = "exit";
+
+        if self.use_simple_node_names() {
+            let node_count = self.body.basic_blocks.iter().map(Self::node_count).sum();
+            return Self::simple_node_name(node_count, node);
+        }
+
+        node.into()
+    }
+
+    // Hack: if we temporarily need simpler node names, while comparing to the manual facts:
+    // use single-letter names.
+    fn use_simple_node_names(&self) -> bool {
+        self.simple_node_names || std::env::var("SIMPLE_NODES").is_ok()
+    }
+
+    fn simple_node_name(node_idx: usize, node: &str) -> Node {
+        let node_idx = 'a' as u32 + node_idx as u32;
+        let node_as_letter = char::from_u32(node_idx).unwrap_or_else(|| {
+            panic!(
+                "Couldn't turn '{}' into a single letter name for node {:?}",
+                node_idx, node
+            )
+        });
+        node_as_letter.to_string().into()
+    }
+
     // Sanity check that no origins are present
     // - in the LHS ty
     // - in borrow expressions on the RHS
@@ -696,89 +2008,153 @@ impl<'a> FactEmitter<'a> {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum Variance {
     Covariant,
-
-    #[allow(dead_code)]
     Contravariant,
-
     Invariant,
 }
 
-trait TyVisitor {
-    fn on_origin_visited(&mut self, origin: &Name) -> ControlFlow<()>;
+impl Variance {
+    // The variance of a position of variance `inner`, nested inside a position of variance
+    // `self`.
+    fn xform(self, inner: Variance) -> Variance {
+        match (self, inner) {
+            (Variance::Covariant, inner) => inner,
+            (Variance::Invariant, _) | (_, Variance::Invariant) => Variance::Invariant,
+            (Variance::Contravariant, Variance::Covariant) => Variance::Contravariant,
+            (Variance::Contravariant, Variance::Contravariant) => Variance::Covariant,
+        }
+    }
+
+    // The variance of a generic used at both `self` and `other` variances.
+    fn join(self, other: Variance) -> Variance {
+        if self == other {
+            self
+        } else {
+            Variance::Invariant
+        }
+    }
+
+    // Records a use of a generic at variance `used_at`, into the variance of its previous uses.
+    fn join_into(variance: &mut Option<Variance>, used_at: Variance) {
+        *variance = Some(match *variance {
+            Some(previous) => previous.join(used_at),
+            None => used_at,
+        });
+    }
 }
 
-impl Ty {
-    fn is_ref(&self) -> bool {
-        matches!(self, Ty::Ref { .. } | Ty::RefMut { .. })
+// The variance of the pointees of a pair of unique references, whose own position has the given
+// `variance`: they are invariant, except when the concrete pointee of a unique reference in a
+// covariant position is coerced into a trait object, e.g. `&'a mut S` into `&'a mut dyn Trait`.
+fn unique_pointee_variance(lhs_ty: &Ty, rhs_ty: &Ty, variance: Variance) -> Variance {
+    match (lhs_ty, rhs_ty, variance) {
+        (Ty::Dyn { .. }, Ty::Dyn { .. }, _) => Variance::Invariant,
+        (Ty::Dyn { .. }, _, Variance::Covariant) => Variance::Covariant,
+        _ => Variance::Invariant,
     }
+}
 
-    // Returns true if this type contains origins, recursively.
-    fn has_origins(&self) -> bool {
-        struct OriginVisitor;
-        impl TyVisitor for OriginVisitor {
-            fn on_origin_visited(&mut self, _origin: &Name) -> ControlFlow<()> {
-                ControlFlow::Break(())
+// Returns the origins transitively reachable from `origin` in the graph of `subsets`, following
+// the subset edges `backwards` or not. The `origin` itself is not included, unless it's part of a
+// cycle.
+fn reachable_origins(
+    subsets: &[(Origin, Origin)],
+    origin: &Origin,
+    backwards: bool,
+) -> Vec<Origin> {
+    let mut reachable: Vec<Origin> = Vec::new();
+    let mut stack = vec![origin.clone()];
+    while let Some(current) = stack.pop() {
+        for (origin1, origin2) in subsets {
+            let (from, to) = if backwards {
+                (origin2, origin1)
+            } else {
+                (origin1, origin2)
+            };
+            if *from == current && !reachable.contains(to) {
+                reachable.push(to.clone());
+                stack.push(to.clone());
             }
         }
-        let mut visitor = OriginVisitor;
-        self.visit_origins(&mut visitor).is_some()
     }
+    reachable
+}
 
-    // Visits all the origins present in this type, recursively.
-    fn visit_origins<V>(&self, visitor: &mut V) -> Option<()>
-    where
-        V: TyVisitor,
-    {
-        match self {
-            Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
-                if let ControlFlow::Break(value) = visitor.on_origin_visited(origin) {
-                    return Some(value);
-                }
+// A call to a function whose signature is known, instantiated at a specific node.
+struct CallInstance {
+    arg_tys: Vec<Ty>,
+    ret_ty: Ty,
 
-                return ty.visit_origins(visitor);
-            }
+    /// The `T: 'a` bounds of the generic types, instantiated with the inferred types and
+    /// fresh origins.
+    bounds: Vec<(Origin, Origin)>,
+}
 
-            Ty::Struct { parameters, .. } => {
-                for param in parameters {
-                    match param {
-                        Parameter::Origin(origin) => {
-                            if let ControlFlow::Break(value) = visitor.on_origin_visited(origin) {
-                                return Some(value);
-                            }
-                        }
-                        Parameter::Ty(ty) => {
-                            return ty.visit_origins(visitor);
-                        }
+// Collects the `T: 'a` bounds of the given generic declarations, instantiated by `subst`: all the
+// origins in the type substituted for `T` must outlive the origin substituted for `'a`.
+fn collect_declared_bounds(
+    generic_decls: &[GenericDecl],
+    subst: &Substitution,
+    bounds: &mut Vec<(Origin, Origin)>,
+) {
+    for generic_decl in generic_decls {
+        if let GenericDecl::Ty {
+            name,
+            bounds: declared_bounds,
+        } = generic_decl
+        {
+            let ty = subst.apply_to_ty(&Ty::Param { name: name.clone() });
+            let mut origins = Vec::new();
+            ty.collect_origins_into(&mut origins);
+            for declared_bound in declared_bounds {
+                let bounding_origin: Origin = subst.apply_to_origin(declared_bound).into();
+                for origin in &origins {
+                    let bound = (origin.clone(), bounding_origin.clone());
+                    if bound.0 != bound.1 && !bounds.contains(&bound) {
+                        bounds.push(bound);
                     }
                 }
             }
+        }
+    }
+}
 
-            Ty::I32 => {}
-            Ty::Unit => {}
+impl Ty {
+    // If this is a function pointer type, returns its binder, argument types and return type.
+    fn as_fn_ptr(&self) -> Option<(&[Name], &[Ty], &Ty)> {
+        match self {
+            Ty::FnPtr {
+                binder,
+                arg_tys,
+                ret_ty,
+            } => Some((binder, arg_tys, ret_ty)),
+            _ => None,
         }
+    }
 
-        None
+    // Returns true if this type contains origins, recursively.
+    fn has_origins(&self) -> bool {
+        let mut has_origins = false;
+        self.visit_origins(CHECKED_ORIGINS, &mut |_| has_origins = true);
+        has_origins
     }
 
     // Collects all the origins present in this type, recursively.
     fn collect_origins_into(&self, origins: &mut Vec<Origin>) {
-        struct OriginCollector<'a> {
-            origins: &'a mut Vec<Origin>,
-        }
-        impl TyVisitor for OriginCollector<'_> {
-            fn on_origin_visited(&mut self, origin: &Name) -> ControlFlow<()> {
-                self.origins.push(origin.into());
-                ControlFlow::Continue(())
-            }
-        }
-        let mut visitor = OriginCollector { origins };
-        self.visit_origins(&mut visitor);
+        self.visit_origins(CHECKED_ORIGINS, &mut |origin| origins.push(origin.into()));
     }
 }
 
+// The origins of a type that the borrow checker tracks: raw pointers carry no origins, their uses
+// are unchecked, and the origins bound by function pointer types are instantiated each time they
+// are used.
+const CHECKED_ORIGINS: OriginsVisited = OriginsVisited {
+    behind_raw_ptrs: false,
+    fn_ptrs: FnPtrOrigins::Free,
+};
+
 // For readability purposes, and conversion to Soufflé facts, display the facts as the
 // textual format.
 impl fmt::Display for Facts {
@@ -800,7 +2176,15 @@ impl fmt::Display for Facts {
             facts_per_node.entry(&node2.0).or_default();
         }
 
-        // Display the facts in the operational order described in the datalog rules.
+        // Display the facts in the operational order described in the datalog rules, after the
+        // markers of cleanup nodes.
+        for node in &self.cleanup_node {
+            facts_per_node
+                .entry(&node.0)
+                .or_default()
+                .push("cleanup_node()".to_string());
+        }
+
         for (origin, node) in &self.access_origin {
             facts_per_node
                 .entry(&node.0)
@@ -808,6 +2192,13 @@ impl fmt::Display for Facts {
                 .push(format!("access_origin({})", origin.0));
         }
 
+        for (origin, node) in &self.drop_access_origin {
+            facts_per_node
+                .entry(&node.0)
+                .or_default()
+                .push(format!("drop_access_origin({})", origin.0));
+        }
+
         for (origin, node) in &self.invalidate_origin {
             facts_per_node
                 .entry(&node.0)
@@ -829,6 +2220,24 @@ impl fmt::Display for Facts {
                 .push(format!("introduce_subset({}, {})", origin1.0, origin2.0));
         }
 
+        for (origin, place, mode, node) in &self.loan_issued_at {
+            facts_per_node.entry(&node.0).or_default().push(format!(
+                "loan_issued_at({}, {:?}, {})",
+                origin.0, place, mode
+            ));
+        }
+
+        // Display the global facts first, separated from the nodes
+        for origin in &self.mark_as_loan_origin {
+            writeln!(f, "mark_as_loan_origin({})", origin.0)?;
+        }
+        for (origin1, origin2) in &self.known_subset {
+            writeln!(f, "known_subset({}, {})", origin1.0, origin2.0)?;
+        }
+        if !self.mark_as_loan_origin.is_empty() || !self.known_subset.is_empty() {
+            writeln!(f)?;
+        }
+
         // Display the indexed data in the frontend format
         for (node_idx, (node, facts)) in facts_per_node.into_iter().enumerate() {
             if node_idx != 0 {
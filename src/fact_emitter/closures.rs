@@ -0,0 +1,149 @@
+//! Desugaring of closures into structs of their captures.
+//!
+//! The closure in `c = |&'L_x x, move y|;` is turned into:
+//! - a struct `closure_c`, with a field per capture: a reference to the place captured by borrow,
+//!   or the place itself when captured by move. All the origins in these fields are generic
+//!   parameters of the struct, named after the closure variable: `'c_L_x` for the borrow above.
+//! - the declaration of the closure variable `c`, whose type is this struct.
+//! - a prototype for the struct's constructor, `closure_c_new`, and a call to this constructor with
+//!   the captures as arguments, replacing the closure expression.
+//!
+//! The loans and subsets of the captures are then emitted like the ones of any other call.
+
+use super::*;
+
+impl FactEmitter<'_> {
+    pub(super) fn desugar_closures(&mut self) {
+        // The closures are desugared in order: a closure can capture the variable of a previous
+        // closure, whose type needs to be known.
+        for block_idx in 0..self.body.basic_blocks.len() {
+            for statement_idx in 0..self.body.basic_blocks[block_idx].statements.len() {
+                let statement = &self.body.basic_blocks[block_idx].statements[statement_idx];
+                let closure = match &**statement {
+                    Statement::Assign(place, Expr::Closure { captures }) => {
+                        assert!(
+                            place.projections.is_empty(),
+                            "Closures can only be assigned to variables, not to {:?}",
+                            place,
+                        );
+                        self.desugar_closure(&place.base, captures)
+                    }
+
+                    Statement::Expr(Expr::Closure { .. }) => {
+                        panic!("Closures must be assigned to a variable")
+                    }
+
+                    _ => continue,
+                };
+
+                let statement = &mut self.body.basic_blocks[block_idx].statements[statement_idx];
+                if let Statement::Assign(_, expr) = &mut **statement {
+                    if let Expr::Closure { captures } = std::mem::replace(expr, Expr::Unit) {
+                        *expr = Expr::Call {
+                            name: closure.constructor.name.clone(),
+                            arguments: captures,
+                        };
+                    }
+                }
+
+                self.program.struct_decls.push(closure.struct_decl);
+                self.body.variables.push(closure.variable);
+                self.program.fn_prototypes.push(closure.constructor);
+            }
+        }
+    }
+
+    fn desugar_closure(&self, variable: &Name, captures: &[Expr]) -> DesugaredClosure {
+        assert!(
+            self.body.variables.iter().all(|v| &v.name != variable),
+            "The type of closure variable {} can't be declared",
+            variable,
+        );
+
+        let struct_name = format!("closure_{}", variable);
+        let fresh_origin =
+            |origin: &Name| format!("'{}_{}", variable, origin.trim_start_matches('\''));
+
+        let mut generics: Vec<Name> = Vec::new();
+        let mut field_decls = Vec::new();
+        for (idx, capture) in captures.iter().enumerate() {
+            let (kind, place) = match capture {
+                Expr::Access { kind, place } => (kind, place),
+                _ => panic!("Closures can only capture places, not {:?}", capture),
+            };
+
+            // The origins in the type of the captured place become generic parameters of the
+            // struct, except for `'static`.
+            let ty = self.ty_of_place(place);
+            let mut origins = Vec::new();
+            ty.collect_origins_into(&mut origins);
+            let mut subst = Substitution::default();
+            for Origin(origin) in origins {
+                if origin == STATIC_ORIGIN {
+                    continue;
+                }
+
+                let renamed_origin = fresh_origin(&origin);
+                subst.bind_origin(&origin, renamed_origin.clone());
+                if !generics.contains(&renamed_origin) {
+                    generics.push(renamed_origin);
+                }
+            }
+            let ty = subst.apply_to_ty(&ty);
+
+            // Captures by borrow are references to the place, with an origin of their own.
+            let ty = match kind {
+                AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin) => {
+                    let origin = fresh_origin(origin);
+                    if !generics.contains(&origin) {
+                        generics.push(origin.clone());
+                    }
+                    let ty = Box::new(ty);
+                    if matches!(kind, AccessKind::Borrow(_)) {
+                        Ty::Ref { origin, ty }
+                    } else {
+                        Ty::RefMut { origin, ty }
+                    }
+                }
+                AccessKind::Copy | AccessKind::Move => ty,
+            };
+
+            field_decls.push(VariableDecl {
+                name: format!("capture{}", idx),
+                ty,
+            });
+        }
+
+        let generic_decls: Vec<_> = generics.iter().cloned().map(GenericDecl::Origin).collect();
+        let closure_ty = Ty::Struct {
+            name: struct_name.clone(),
+            parameters: generics.into_iter().map(Parameter::Origin).collect(),
+        };
+
+        DesugaredClosure {
+            constructor: FnPrototype {
+                name: format!("{}_new", struct_name),
+                generic_decls: generic_decls.clone(),
+                arg_tys: field_decls.iter().map(|field| field.ty.clone()).collect(),
+                ret_ty: closure_ty.clone(),
+            },
+            struct_decl: StructDecl {
+                name: struct_name,
+                generic_decls,
+                field_decls,
+                drop_impl: None,
+            },
+            variable: VariableDecl {
+                name: variable.clone(),
+                ty: closure_ty,
+            },
+        }
+    }
+}
+
+// The items generated for a closure expression.
+struct DesugaredClosure {
+    struct_decl: StructDecl,
+    variable: VariableDecl,
+    constructor: FnPrototype,
+}
@@ -0,0 +1,321 @@
+//! Drops, and the origins a destructor may observe.
+//!
+//! Dropping a value runs the destructors in its type, which can observe the references they
+//! contain: the origins of these references are drop-accessed when the value is dropped, and are
+//! only required to be live until the drop, not used like regular accesses. Dropping a
+//! value without destructors accesses no origins: e.g. dropping a reference doesn't require it to
+//! be valid, and the reference can dangle.
+//!
+//! When a struct has a `Drop` impl, or is not declared:
+//! - its destructor can observe all its generic parameters, except the ones marked with
+//!   `#[may_dangle]`, which it promises not to access
+//! - its fields are then dropped, running their own destructors, like the fields of structs
+//!   without a destructor
+//!
+//! Values are dropped by `drop(place);` statements, and the locals are implicitly dropped at the
+//! end of their scope: at their `StorageDead(x);` statement, or else when returning from the
+//! function. A local that was moved out or dropped as a whole, on every path reaching the end of
+//! its scope, is not dropped again.
+
+use super::*;
+
+impl FactEmitter<'_> {
+    // Emit the facts of dropping the value in `place`: the origins the destructors may observe
+    // are drop-accessed, and the place's memory is freed, invalidating the loans of the place and
+    // its sub-places. Loans of places behind a deref of the dropped place don't borrow from its
+    // memory.
+    pub(super) fn emit_drop_facts(&self, node: &Node, place: &Place, facts: &mut Facts) {
+        assert!(!self.is_static(place), "Can't drop static {}", place.base);
+
+        self.emit_drop_access_facts(node, place, facts);
+
+        for (origin, loan_place) in self.loans_of_place_and_subplaces(place) {
+            let projections = &loan_place.projections[place.projections.len()..];
+            if !projections.contains(&Projection::Deref) {
+                facts.invalidate_origin.push((origin, node.clone()));
+            }
+        }
+    }
+
+    // Emit the facts of the end of the local's scope: it's implicitly dropped, unless it was
+    // moved out on every path reaching it, and its memory is freed, invalidating the loans of the
+    // local and its sub-places. Loans of places behind a deref don't borrow from the local's
+    // memory.
+    pub(super) fn emit_storage_dead_facts(&self, node: &Node, variable: &Name, facts: &mut Facts) {
+        assert!(
+            self.body.variables.iter().any(|v| &v.name == variable),
+            "Can't end the scope of {}, which is not a local",
+            variable
+        );
+        let place = Place {
+            base: variable.clone(),
+            projections: vec![],
+        };
+
+        if !self.moved_out[node].contains(variable) {
+            self.emit_drop_access_facts(node, &place, facts);
+        }
+
+        for (origin, loan_place) in self.loans_of_place_and_subplaces(&place) {
+            if !loan_place.projections.contains(&Projection::Deref) {
+                facts.invalidate_origin.push((origin, node.clone()));
+            }
+        }
+    }
+
+    // Emit the access facts of dropping the value in `place`: `drop_access_origin` for the
+    // origins the destructors may observe.
+    pub(super) fn emit_drop_access_facts(&self, node: &Node, place: &Place, facts: &mut Facts) {
+        // Reaching the dropped value through derefs and fields uses the origins along the path,
+        // like reads do.
+        if let Some((_, parent_projections)) = place.projections.split_last() {
+            let parent = Place {
+                base: place.base.clone(),
+                projections: parent_projections.to_vec(),
+            };
+            for origin in self.origins_of_place(&parent) {
+                facts.access_origin.push((origin, node.clone()));
+            }
+        }
+
+        if self.derefs_raw_ptr(place) {
+            return;
+        }
+
+        let mut origins = Vec::new();
+        self.collect_drop_origins(&self.ty_of_place(place), &mut origins);
+        for (idx, origin) in origins.iter().enumerate() {
+            if !origins[..idx].contains(origin) {
+                facts
+                    .drop_access_origin
+                    .push((origin.clone(), node.clone()));
+            }
+        }
+    }
+
+    // Collect the origins the destructors in `ty` may observe when a value of this type is
+    // dropped.
+    fn collect_drop_origins(&self, ty: &Ty, origins: &mut Vec<Origin>) {
+        match ty {
+            Ty::Struct { name, parameters } => {
+                // Undeclared structs have an unknown destructor, which may observe all their
+                // parameters.
+                let decl = match self.struct_decl(name) {
+                    Some(decl) => decl,
+                    None => return ty.collect_origins_into(origins),
+                };
+                let subst = Substitution::new(&decl.generic_decls, parameters);
+
+                if let Some(drop_impl) = &decl.drop_impl {
+                    for (decl, parameter) in decl.generic_decls.iter().zip(parameters) {
+                        let (GenericDecl::Origin(name) | GenericDecl::Ty { name, .. }) = decl;
+                        match parameter {
+                            // The destructor can't observe dangling origins
+                            Parameter::Origin(_) if drop_impl.may_dangle.contains(name) => {}
+                            Parameter::Origin(origin) => origins.push(origin.into()),
+
+                            // The destructor doesn't access dangling types, but the values of
+                            // these types can still be dropped by the destructor.
+                            Parameter::Ty(ty) if drop_impl.may_dangle.contains(name) => {
+                                self.collect_drop_origins(ty, origins)
+                            }
+                            Parameter::Ty(ty) => ty.collect_origins_into(origins),
+                        }
+                    }
+                }
+
+                for field in &decl.field_decls {
+                    self.collect_drop_origins(&subst.apply_to_ty(&field.ty), origins);
+                }
+            }
+
+            // The trait object's destructor can observe anything its bound allows
+            Ty::Dyn { origin, .. } => origins.push(origin.into()),
+
+            // References, raw pointers and function pointers have no destructors, and neither
+            // does the pointee of references and pointers: they don't own it.
+            Ty::Ref { .. }
+            | Ty::RefMut { .. }
+            | Ty::RawPtr { .. }
+            | Ty::RawPtrMut { .. }
+            | Ty::FnPtr { .. }
+            | Ty::Param { .. }
+            | Ty::I32
+            | Ty::Bool
+            | Ty::Unit => {}
+        }
+    }
+
+    // The variables implicitly dropped when returning at `node`: all the locals whose scope didn't
+    // end earlier, except the return place whose value is moved out to the caller, and the locals
+    // moved out on every path reaching the return.
+    pub(super) fn implicitly_dropped_variables(&self, node: &Node) -> Vec<&Name> {
+        let moved_out = &self.moved_out[node];
+        let storage_dead = self.storage_dead_variables();
+        self.body
+            .variables
+            .iter()
+            .map(|v| &v.name)
+            .filter(|name| {
+                *name != RETURN_PLACE && !moved_out.contains(name) && !storage_dead.contains(name)
+            })
+            .collect()
+    }
+
+    // The variables whose scope ends before the function returns, with a `StorageDead`.
+    pub(super) fn storage_dead_variables(&self) -> Vec<&Name> {
+        let mut storage_dead = Vec::new();
+        for bb in &self.body.basic_blocks {
+            for s in &bb.statements {
+                if let Statement::StorageDead(variable) = &**s {
+                    storage_dead.push(variable);
+                }
+            }
+        }
+        storage_dead
+    }
+
+    // Collect the locals moved out as a whole on every path reaching each node of the body: the
+    // locals are initialized when entering the function, moving the whole local out or dropping
+    // it deinitializes it, and assigning to it initializes it again. Only the locals that are not
+    // initialized on any path are known not to be dropped at the end of their scope.
+    pub(super) fn collect_moved_out_variables(&self) -> HashMap<Node, Vec<Name>> {
+        let blocks = &self.body.basic_blocks;
+        let block_idx = |name: &Name| {
+            blocks
+                .iter()
+                .position(|bb| &bb.name == name)
+                .unwrap_or_else(|| panic!("Can't find block {}", name))
+        };
+
+        // The locals moved out on entry to each block, until the fixpoint is reached: the
+        // intersection of the locals moved out on each incoming edge. The blocks not reached yet
+        // have no incoming paths.
+        let mut on_entry: Vec<Option<Vec<Name>>> = vec![None; blocks.len()];
+        if let Some(entry) = on_entry.first_mut() {
+            *entry = Some(Vec::new());
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (idx, bb) in blocks.iter().enumerate() {
+                let mut moved_out = match &on_entry[idx] {
+                    Some(moved_out) => moved_out.clone(),
+                    None => continue,
+                };
+                for s in &bb.statements {
+                    move_out_in_statement(s, &mut moved_out);
+                }
+
+                // A call moves its arguments out even when it unwinds, but only initializes its
+                // destination when it returns.
+                let edges = match &bb.terminator {
+                    Terminator::Call {
+                        call,
+                        target,
+                        unwind,
+                    } => {
+                        if let Statement::Assign(_, expr) | Statement::Expr(expr) = &**call {
+                            collect_moved_out_variables(expr, &mut moved_out);
+                        }
+                        let unwinding = unwind.iter().map(|unwind| (unwind, moved_out.clone()));
+                        let mut returning = moved_out.clone();
+                        move_out_in_statement(call, &mut returning);
+                        std::iter::once((target, returning))
+                            .chain(unwinding)
+                            .collect()
+                    }
+                    _ => bb
+                        .successors()
+                        .into_iter()
+                        .map(|successor| (successor, moved_out.clone()))
+                        .collect::<Vec<_>>(),
+                };
+
+                for (successor, moved_out) in edges {
+                    let successor = &mut on_entry[block_idx(successor)];
+                    let moved_out = match successor {
+                        Some(previous) => previous
+                            .iter()
+                            .filter(|variable| moved_out.contains(variable))
+                            .cloned()
+                            .collect(),
+                        None => moved_out,
+                    };
+                    if successor.as_ref() != Some(&moved_out) {
+                        *successor = Some(moved_out);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // The locals moved out before each of the blocks' statements, and before their terminator
+        let mut moved_out_at_nodes = HashMap::new();
+        for (bb, moved_out) in blocks.iter().zip(on_entry) {
+            let mut moved_out = moved_out.unwrap_or_default();
+            for (idx, s) in bb.statements.iter().enumerate() {
+                moved_out_at_nodes.insert(self.node_at(&bb.name, idx), moved_out.clone());
+                move_out_in_statement(s, &mut moved_out);
+            }
+            moved_out_at_nodes.insert(self.node_at(&bb.name, bb.statements.len()), moved_out);
+        }
+        moved_out_at_nodes
+    }
+}
+
+// Update the locals moved out as a whole, after evaluating `statement`.
+fn move_out_in_statement(statement: &Statement, moved_out: &mut Vec<Name>) {
+    match statement {
+        Statement::Assign(place, expr) => {
+            collect_moved_out_variables(expr, moved_out);
+            if place.projections.is_empty() {
+                moved_out.retain(|variable| *variable != place.base);
+            }
+        }
+        Statement::Expr(expr) => collect_moved_out_variables(expr, moved_out),
+        Statement::Drop(place) if place.projections.is_empty() => {
+            if !moved_out.contains(&place.base) {
+                moved_out.push(place.base.clone());
+            }
+        }
+        Statement::Drop(_) | Statement::StorageDead(_) => {}
+    }
+}
+
+// Collect the variables whose value is moved out as a whole by `expr`, including in call
+// arguments.
+fn collect_moved_out_variables(expr: &Expr, moved_out: &mut Vec<Name>) {
+    match expr {
+        Expr::Access {
+            kind: AccessKind::Move,
+            place,
+        } if place.projections.is_empty() => {
+            if !moved_out.contains(&place.base) {
+                moved_out.push(place.base.clone());
+            }
+        }
+        Expr::Call { arguments, .. }
+        | Expr::Closure {
+            captures: arguments,
+        } => {
+            for argument in arguments {
+                collect_moved_out_variables(argument, moved_out);
+            }
+        }
+        Expr::Cast { expr, .. }
+        | Expr::BorrowRvalue { rvalue: expr, .. }
+        | Expr::UnaryOp { operand: expr, .. } => collect_moved_out_variables(expr, moved_out),
+        Expr::BinaryOp { lhs, rhs, .. } => {
+            collect_moved_out_variables(lhs, moved_out);
+            collect_moved_out_variables(rhs, moved_out);
+        }
+        Expr::Struct { fields, .. } => {
+            for (_, expr) in fields {
+                collect_moved_out_variables(expr, moved_out);
+            }
+        }
+        Expr::Access { .. } | Expr::Number { .. } | Expr::Unit => {}
+    }
+}
@@ -19,6 +19,9 @@ fn canonical_liveness() {
         }
     ";
     assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_p)
+    mark_as_loan_origin('L_q)
+
     a: "p = 22" {
     	invalidate_origin('L_p)
     	goto b
@@ -33,6 +36,7 @@ fn canonical_liveness() {
     	clear_origin('x)
     	clear_origin('L_p)
     	introduce_subset('L_p, 'x)
+    	loan_issued_at('L_p, "p", shared)
     	goto d
     }
 
@@ -40,6 +44,7 @@ fn canonical_liveness() {
     	clear_origin('x)
     	clear_origin('L_q)
     	introduce_subset('L_q, 'x)
+    	loan_issued_at('L_q, "q", shared)
     	goto e
     }
 
@@ -70,6 +75,8 @@ fn canonical_liveness_err() {
         }
     ";
     assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_p)
+
     a: "p = 22" {
     	invalidate_origin('L_p)
     	goto b
@@ -79,6 +86,7 @@ fn canonical_liveness_err() {
     	clear_origin('x)
     	clear_origin('L_p)
     	introduce_subset('L_p, 'x)
+    	loan_issued_at('L_p, "p", shared)
     	goto c
     }
 
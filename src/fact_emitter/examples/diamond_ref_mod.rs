@@ -0,0 +1,66 @@
+use super::*;
+use insta::assert_display_snapshot;
+
+#[test]
+// Port of /polonius.next/tests/diamond-ref-mod/program.txt
+fn diamond_ref_mod() {
+    let program = "
+        let x: i32;
+        let p: &'p i32;
+
+        bb0: {
+            x = 42;
+            p = &'const 22;
+            goto bb1, bb2;
+        }
+
+        bb1: {
+            p = &'L_x x;
+            goto bb3;
+        }
+
+        bb2: {
+            x = 0;
+            goto bb3;
+        }
+
+        bb3: {
+            use(copy p);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('const)
+    mark_as_loan_origin('L_x)
+
+    a: "x = 42" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "p = &'const 22" {
+    	clear_origin('p)
+    	clear_origin('const)
+    	introduce_subset('const, 'p)
+    	loan_issued_at('const, "promoted0", shared)
+    	goto c d
+    }
+
+    c: "p = &'L_x x" {
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	goto e
+    }
+
+    d: "x = 0" {
+    	invalidate_origin('L_x)
+    	goto e
+    }
+
+    e: "use(copy p)" {
+    	access_origin('p)
+    	goto
+    }
+    "###);
+}
@@ -38,14 +38,18 @@ fn issue_47680() {
     // Notes about the current output:
     // - node b: missing subset because of the deref
     // - node c: missing subset between the arguments, the fn signatures lack lifetime bounds
-    // - node d: missing clear origin of a loan of the deref
 
     assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_Thing)
+    mark_as_loan_origin('L_*temp)
+
     a: "temp = &'L_Thing mut thing" {
     	invalidate_origin('L_Thing)
     	clear_origin('temp)
+    	clear_origin('L_*temp)
     	clear_origin('L_Thing)
     	introduce_subset('L_Thing, 'temp)
+    	loan_issued_at('L_Thing, "thing", mutable)
     	goto b
     }
 
@@ -55,6 +59,7 @@ fn issue_47680() {
     	clear_origin('t0)
     	clear_origin('L_*temp)
     	introduce_subset('L_*temp, 't0)
+    	loan_issued_at('L_*temp, "*temp", mutable)
     	goto c
     }
 
@@ -67,6 +72,7 @@ fn issue_47680() {
     d: "temp = move v" {
     	access_origin('v)
     	clear_origin('temp)
+    	clear_origin('L_*temp)
     	introduce_subset('v, 'temp)
     	goto f
     }
@@ -1,7 +1,9 @@
 //! Tests porting the existing examples using the manual fact format, to the new frontend format
 
 mod canonical_liveness;
+mod diamond_ref_mod;
 mod issue_47680;
+mod self_invalidation_loop;
 mod vec_temp;
 
 use super::test::*;
@@ -23,6 +25,8 @@ fn example_a() {
     ";
 
     assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+
     a: "x = 3" {
     	invalidate_origin('L_x)
     	goto b
@@ -32,6 +36,7 @@ fn example_a() {
     	clear_origin('y)
     	clear_origin('L_x)
     	introduce_subset('L_x, 'y)
+    	loan_issued_at('L_x, "x", shared)
     	goto c
     }
 
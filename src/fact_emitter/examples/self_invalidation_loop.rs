@@ -0,0 +1,98 @@
+use super::*;
+use insta::assert_display_snapshot;
+
+#[test]
+// Port of /polonius.next/tests/self-invalidation-loop/program.txt, in the structured syntax
+fn self_invalidation_loop() {
+    let program = "
+        struct Vec<T> {
+            item0: T,
+        }
+        fn Vec_new<T>() -> Vec<T>;
+        fn Vec_push<T>(v: &mut Vec<T>, item: T) -> ();
+        fn maybe() -> bool;
+
+        let x: i32 = 22;
+        let v: Vec<&mut i32> = Vec_new();
+        while maybe() {
+            let p: &mut i32 = &mut x;
+            let v_tmp: &mut Vec<&mut i32> = &mut v;
+            Vec_push(move v_tmp, move p);
+        }
+    ";
+
+    // Notes about the current output:
+    // - the locals of the loop body are dead at the end of each iteration, which invalidates no
+    //   loans: they're references, and their own memory is not borrowed.
+
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_v)
+    known_subset('v_tmp2, 'v_tmp)
+
+    a: "let x: i32 = 22" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "let v: Vec<&mut i32> = Vec_new()" {
+    	invalidate_origin('L_v)
+    	clear_origin('v)
+    	goto c
+    }
+
+    c: "maybe()" {
+    	goto d i
+    }
+
+    d: "let p: &mut i32 = &mut x" {
+    	invalidate_origin('L_x)
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", mutable)
+    	goto e
+    }
+
+    e: "let v_tmp: &mut Vec<&mut i32> = &mut v" {
+    	access_origin('v)
+    	invalidate_origin('L_v)
+    	clear_origin('v_tmp)
+    	clear_origin('v_tmp2)
+    	clear_origin('L_v)
+    	introduce_subset('L_v, 'v_tmp)
+    	introduce_subset('v, 'v_tmp2)
+    	introduce_subset('v_tmp2, 'v)
+    	loan_issued_at('L_v, "v", mutable)
+    	goto f
+    }
+
+    f: "Vec_push(move v_tmp, move p)" {
+    	access_origin('v_tmp)
+    	access_origin('v_tmp2)
+    	access_origin('p)
+    	clear_origin('Vec_push_1_f)
+    	introduce_subset('v_tmp, 'Vec_push_1_f)
+    	introduce_subset('p, 'v_tmp2)
+    	goto g
+    }
+
+    g: "}" {
+    	goto h
+    }
+
+    h: "}" {
+    	goto c
+    }
+
+    i: "return" {
+    	invalidate_origin('L_v)
+    	invalidate_origin('L_x)
+    	goto j
+    }
+
+    j: "(pass)" {
+    	goto
+    }
+    "###);
+}
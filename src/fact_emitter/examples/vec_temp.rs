@@ -30,6 +30,10 @@ fn vec_temp() {
     // - node e: missing subset between the call's arguments, the fn signatures lack lifetime bounds
 
     assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_v)
+    known_subset('tmp1, 'tmp0)
+
     a: "x = 22" {
     	invalidate_origin('L_x)
     	goto b
@@ -45,6 +49,7 @@ fn vec_temp() {
     	clear_origin('p)
     	clear_origin('L_x)
     	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", shared)
     	goto d
     }
 
@@ -57,6 +62,7 @@ fn vec_temp() {
     	introduce_subset('L_v, 'tmp0)
     	introduce_subset('v, 'tmp1)
     	introduce_subset('tmp1, 'v)
+    	loan_issued_at('L_v, "v", mutable)
     	goto e
     }
 
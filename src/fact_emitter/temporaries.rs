@@ -0,0 +1,249 @@
+//! Temporaries holding borrowed rvalues.
+//!
+//! Borrowing an rvalue, like in `&'L 22` or `&'L f()`, stores it in a temporary and borrows the
+//! temporary:
+//! - constants are promoted to `'static` data: shared borrows of numbers and `()` borrow a
+//!   constant `promoted{N}`, whose loans are never invalidated.
+//! - the other rvalues are stored in a local `tmp{N}`, whose type is the type of the rvalue at the
+//!   node where it's evaluated: e.g. the return type of the call, with the call's fresh origins.
+//!
+//! Like in rustc, the local temporaries are dead at the end of their statement, where a
+//! `StorageDead(tmp{N})` is inserted: they're dropped, and their loans invalidated. The
+//! temporaries of a call terminator's arguments are dead when the call returns, at the start of
+//! its `return` block. The temporaries borrowed by a `let` initializer are extended to live as
+//! long as its variable: they're dead after the variable's own `StorageDead`, or else when
+//! returning.
+
+use super::*;
+use crate::span::Span;
+
+impl FactEmitter<'_> {
+    pub(super) fn declare_temporaries(&mut self) {
+        // The temporaries are named, and the end of their scopes inserted, before declaring them:
+        // their types depend on the nodes where they're evaluated, which are then known.
+        self.end_temporary_scopes();
+
+        for block_idx in 0..self.body.basic_blocks.len() {
+            let bb = &self.body.basic_blocks[block_idx];
+            for statement_idx in 0..=bb.statements.len() {
+                let bb = &self.body.basic_blocks[block_idx];
+                let node = self.node_at(&bb.name, statement_idx);
+
+                // The call terminator is evaluated at the node after the block's statements
+                let statement = match (bb.statements.get(statement_idx), &bb.terminator) {
+                    (Some(statement), _) => (**statement).clone(),
+                    (None, Terminator::Call { call, .. }) => (**call).clone(),
+                    (None, _) => continue,
+                };
+                if let Statement::Assign(_, expr) | Statement::Expr(expr) = &statement {
+                    self.declare_temporaries_in(&node, expr);
+                }
+            }
+        }
+    }
+
+    // Names the temporaries of the borrowed rvalues, and inserts the `StorageDead` statements
+    // ending the scopes of the local ones.
+    fn end_temporary_scopes(&mut self) {
+        let mut temporary_count = 0;
+
+        // The extended temporaries of each variable, and the temporaries of call terminators,
+        // with the block the call returns to.
+        let mut extended_temporaries: HashMap<Name, Vec<Name>> = HashMap::new();
+        let mut call_temporaries: Vec<(Name, Vec<Name>)> = Vec::new();
+
+        for bb in &mut self.body.basic_blocks {
+            let mut statements = Vec::new();
+            for mut statement in std::mem::take(&mut bb.statements) {
+                let mut temporaries = Vec::new();
+                if let Statement::Assign(_, expr) | Statement::Expr(expr) = &mut *statement {
+                    name_temporaries(expr, &mut temporary_count, &mut temporaries);
+                }
+
+                let mut dead_temporaries = Vec::new();
+                for (temporary, extended) in temporaries {
+                    match &*statement {
+                        Statement::Assign(place, _) if extended => extended_temporaries
+                            .entry(place.base.clone())
+                            .or_default()
+                            .push(temporary),
+                        _ => dead_temporaries.push(temporary),
+                    }
+                }
+                statements.push(statement);
+                statements.extend(storage_dead_statements(&dead_temporaries));
+            }
+            bb.statements = statements;
+
+            if let Terminator::Call { call, target, .. } = &mut bb.terminator {
+                let mut temporaries = Vec::new();
+                if let Statement::Assign(_, expr) | Statement::Expr(expr) = &mut **call {
+                    name_temporaries(expr, &mut temporary_count, &mut temporaries);
+                }
+                let temporaries = temporaries.into_iter().map(|(name, _)| name).collect();
+                call_temporaries.push((target.clone(), temporaries));
+            }
+        }
+
+        for (target, temporaries) in call_temporaries {
+            let bb = self
+                .body
+                .basic_blocks
+                .iter_mut()
+                .find(|bb| bb.name == target)
+                .unwrap_or_else(|| panic!("Can't find block {}", target));
+            bb.statements
+                .splice(0..0, storage_dead_statements(&temporaries));
+        }
+
+        for bb in &mut self.body.basic_blocks {
+            let mut statements = Vec::new();
+            for statement in std::mem::take(&mut bb.statements) {
+                let temporaries = match &*statement {
+                    Statement::StorageDead(variable) => extended_temporaries.get(variable),
+                    _ => None,
+                };
+                let temporaries = temporaries.cloned().unwrap_or_default();
+                statements.push(statement);
+                statements.extend(storage_dead_statements(&temporaries));
+            }
+            bb.statements = statements;
+        }
+    }
+
+    // Declares the temporaries of the rvalues borrowed in `expr`, evaluated at `node`.
+    fn declare_temporaries_in(&mut self, node: &Node, expr: &Expr) {
+        match expr {
+            Expr::BorrowRvalue {
+                kind,
+                rvalue,
+                temporary,
+                ..
+            } => {
+                // The rvalue's own temporaries are needed to know its type
+                self.declare_temporaries_in(node, rvalue);
+
+                let ty = self
+                    .ty_of_expr(node, rvalue)
+                    .unwrap_or_else(|| panic!("Can't borrow {:?}, whose type is unknown", rvalue));
+                let name = temporary
+                    .clone()
+                    .expect("Temporaries are named before being declared");
+
+                let variables = self.body.variables.iter().map(|v| &v.name);
+                let statics = self.program.static_decls.iter().map(|s| &s.name);
+                assert!(
+                    variables.chain(statics).all(|candidate| *candidate != name),
+                    "The temporary {} can't be declared",
+                    name,
+                );
+
+                if is_promoted(kind, rvalue) {
+                    self.program.static_decls.push(StaticDecl { name, ty });
+                } else {
+                    self.body.variables.push(VariableDecl { name, ty });
+                }
+            }
+
+            Expr::Call { arguments, .. } => {
+                for argument in arguments {
+                    self.declare_temporaries_in(node, argument);
+                }
+            }
+
+            Expr::Cast { expr, .. } | Expr::UnaryOp { operand: expr, .. } => {
+                self.declare_temporaries_in(node, expr)
+            }
+
+            Expr::BinaryOp { lhs, rhs, .. } => {
+                self.declare_temporaries_in(node, lhs);
+                self.declare_temporaries_in(node, rhs);
+            }
+
+            Expr::Struct { fields, .. } => {
+                for (_, expr) in fields {
+                    self.declare_temporaries_in(node, expr);
+                }
+            }
+
+            Expr::Access { .. } | Expr::Number { .. } | Expr::Unit | Expr::Closure { .. } => {}
+        }
+    }
+}
+
+// Names the temporaries of the rvalues borrowed in `expr`, in evaluation order, and collects the
+// local ones, with whether their lifetime is extended.
+fn name_temporaries(expr: &mut Expr, count: &mut usize, temporaries: &mut Vec<(Name, bool)>) {
+    match expr {
+        Expr::BorrowRvalue {
+            kind,
+            rvalue,
+            temporary,
+            extended,
+        } => {
+            name_temporaries(rvalue, count, temporaries);
+
+            let promoted = is_promoted(kind, rvalue);
+            let prefix = if promoted { "promoted" } else { "tmp" };
+            let name = format!("{}{}", prefix, count);
+            *count += 1;
+            if !promoted {
+                temporaries.push((name.clone(), *extended));
+            }
+            *temporary = Some(name);
+        }
+
+        Expr::Call { arguments, .. } => {
+            for argument in arguments {
+                name_temporaries(argument, count, temporaries);
+            }
+        }
+
+        Expr::Cast { expr, .. } | Expr::UnaryOp { operand: expr, .. } => {
+            name_temporaries(expr, count, temporaries)
+        }
+
+        Expr::BinaryOp { lhs, rhs, .. } => {
+            name_temporaries(lhs, count, temporaries);
+            name_temporaries(rhs, count, temporaries);
+        }
+
+        Expr::Struct { fields, .. } => {
+            for (_, expr) in fields {
+                name_temporaries(expr, count, temporaries);
+            }
+        }
+
+        Expr::Access { .. } | Expr::Number { .. } | Expr::Unit | Expr::Closure { .. } => {}
+    }
+}
+
+// Returns true if the borrowed rvalue is a constant promoted to `'static` data: shared borrows of
+// numbers and `()`.
+fn is_promoted(kind: &AccessKind, rvalue: &Expr) -> bool {
+    matches!(kind, AccessKind::Borrow(_)) && matches!(rvalue, Expr::Number { .. } | Expr::Unit)
+}
+
+// The statements ending the scopes of the temporaries, in reverse order of their declaration.
+// They're introduced by the emitter, and have no span in the input.
+fn storage_dead_statements(temporaries: &[Name]) -> Vec<Sp<Statement>> {
+    temporaries
+        .iter()
+        .rev()
+        .map(|temporary| Sp::new(Statement::StorageDead(temporary.clone()), Span::dummy()))
+        .collect()
+}
+
+// The borrow of the temporary holding the rvalue of a `BorrowRvalue` expression.
+pub(super) fn temporary_borrow(kind: &AccessKind, temporary: &Option<Name>) -> Expr {
+    let temporary = temporary
+        .as_ref()
+        .expect("Temporaries are declared before emitting facts");
+    Expr::Access {
+        kind: kind.clone(),
+        place: Place {
+            base: temporary.clone(),
+            projections: vec![],
+        },
+    }
+}
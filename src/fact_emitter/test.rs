@@ -2,8 +2,11 @@
 mod access_origin;
 mod cfg_edge;
 mod clear_origin;
+mod drop_access_origin;
 mod introduce_subset;
 mod invalidate_origin;
+mod known_subset;
+mod loan_issued_at;
 
 use super::*;
 use crate::ast_parser::test::expect_parse;
@@ -12,7 +15,22 @@ use insta::assert_debug_snapshot;
 
 pub(crate) fn expect_facts(input: &str) -> Facts {
     let program = expect_parse(input);
-    let emitter = FactEmitter::new(program, input, true);
+    assert_eq!(program.bodies.len(), 1, "Expected a single body");
+    let emitter = FactEmitter::new(program, 0, input, true);
+    let mut facts = Default::default();
+    emitter.emit_facts(&mut facts);
+    facts
+}
+
+// Returns the facts of the body of the function `name`, in a program with multiple bodies.
+pub(crate) fn expect_fn_facts(input: &str, name: &str) -> Facts {
+    let program = expect_parse(input);
+    let body_idx = program
+        .bodies
+        .iter()
+        .position(|body| matches!(&body.signature, Some(s) if s.name == name))
+        .unwrap_or_else(|| panic!("Can't find the body of {}", name));
+    let emitter = FactEmitter::new(program, body_idx, input, true);
     let mut facts = Default::default();
     emitter.emit_facts(&mut facts);
     facts
@@ -20,14 +38,14 @@ pub(crate) fn expect_facts(input: &str) -> Facts {
 
 fn create_emitter(input: &str) -> FactEmitter {
     let program = expect_parse(input);
-    FactEmitter::new(program, input, true)
+    FactEmitter::new(program, 0, input, true)
 }
 
 // Returns the type of the given place's path in the given program.
 fn find_ty(program: &str, path: &str) -> Ty {
     let emitter = create_emitter(program);
     let place = parse::place(path).expect("Invalid place");
-    emitter.ty_of_place(&place).clone()
+    emitter.ty_of_place(&place)
 }
 
 // Returns the origins present in the type of the given place's path in the given program.
@@ -86,23 +104,33 @@ fn type_of_vars() {
     }
     "###);
 
-    // TODO ?
-    // // generic struct: origins and types
-    // let program = "
-    //     struct Vec<T> { e: T }
-    //     struct Ref<'a, T> { ref: &'a T }
-    //     let r: Ref<'r, Vec<i32>>;
-    // ";
-    // assert_debug_snapshot!(find_ty(program, "r.ref"), @"");
-
-    // TODO ?
-    // // generic struct: origins and types, and derefs
-    // let program = "
-    //     struct Vec<T> { e: T }
-    //     struct Ref<'a, T> { ref: &'a T }
-    //     let r: Ref<'r, Vec<i32>>;
-    // ";
-    // assert_eq!(find_ty(program, "(*r.ref).e"), Ty::I32);
+    // generic struct: origins and types
+    let program = "
+        struct Vec<T> { e: T }
+        struct Ref<'a, T> { ref: &'a T }
+        let r: Ref<'r, Vec<i32>>;
+    ";
+    assert_debug_snapshot!(find_ty(program, "r.ref"), @r###"
+    Ref {
+        origin: "'r",
+        ty: Struct {
+            name: "Vec",
+            parameters: [
+                Ty(
+                    I32,
+                ),
+            ],
+        },
+    }
+    "###);
+
+    // generic struct: origins and types, and derefs
+    let program = "
+        struct Vec<T> { e: T }
+        struct Ref<'a, T> { ref: &'a T }
+        let r: Ref<'r, Vec<i32>>;
+    ";
+    assert_eq!(find_ty(program, "(*r.ref).e"), Ty::I32);
 }
 
 #[test]
@@ -139,6 +167,27 @@ fn type_of_fields() {
         let a: A<B<C<i32>>>;
     ";
     assert_eq!(find_ty(program, "a.b.c.d"), Ty::I32);
+
+    let program = "
+        struct A<'a, T> { b: &'a mut Vec<T> }
+        let a: A<'x, &'y i32>;
+    ";
+    assert_debug_snapshot!(find_ty(program, "a.b"), @r###"
+    RefMut {
+        origin: "'x",
+        ty: Struct {
+            name: "Vec",
+            parameters: [
+                Ty(
+                    Ref {
+                        origin: "'y",
+                        ty: I32,
+                    },
+                ),
+            ],
+        },
+    }
+    "###);
 }
 
 #[test]
@@ -161,4 +210,8 @@ fn origins_in_ty() {
         find_origins("let f: &'f Vec<&'e Vec<&'d i32>>;", "f"),
         [Origin::from("'f"), Origin::from("'e"), Origin::from("'d")]
     );
+    assert_eq!(
+        find_origins("let g: Pair<&'g i32, Ref<'h>>;", "g"),
+        [Origin::from("'g"), Origin::from("'h")]
+    );
 }
@@ -1,5 +1,5 @@
 use super::*;
-use insta::assert_debug_snapshot;
+use insta::{assert_debug_snapshot, assert_display_snapshot};
 
 #[test]
 fn assignments_read_rhs() {
@@ -154,3 +154,132 @@ fn mutable_borrows_are_considered_writes() {
     ]
     "###);
 }
+
+#[test]
+fn raw_pointers_are_unchecked() {
+    // Casting a borrow to a raw pointer accesses the loan once, which is then not tracked anymore:
+    // the assignment to `x` doesn't invalidate it, and derefs of the raw pointer access no origins.
+    let program = "
+        let x: i32;
+        let y: i32;
+        let p: *const &'p i32;
+        let q: *mut i32;
+        bb0: {
+            q = &'L_x mut x as *mut i32;
+            x = 1;
+            y = copy *q;
+            y = copy **p;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "q = &'L_x mut x as *mut i32" {
+    	access_origin('L_x)
+    	clear_origin('L_x)
+    	goto b
+    }
+
+    b: "x = 1" {
+    	goto c
+    }
+
+    c: "y = copy *q" {
+    	goto d
+    }
+
+    d: "y = copy **p" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "without an explicit cast")]
+fn raw_pointers_require_explicit_casts() {
+    let program = "
+        let x: i32;
+        let p: *const i32;
+        bb0: {
+            p = &'L_x x;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+#[should_panic(expected = "Can't cast")]
+fn raw_pointer_casts_keep_mutability() {
+    let program = "
+        let x: i32;
+        let p: *mut i32;
+        bb0: {
+            p = &'L_x x as *mut i32;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn operators_read_their_operands() {
+    let program = "
+        struct S<'a> {
+            r: &'a i32,
+        }
+
+        let p: &'p i32;
+        let s: S<'s>;
+        let x: i32;
+        let b: bool;
+
+        bb0: {
+            x = copy *p + 1;
+            b = copy x < copy *s.r;
+            b = !move b;
+            x = -copy x;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "x = copy *p + 1" {
+    	access_origin('p)
+    	goto b
+    }
+
+    b: "b = copy x < copy *s.r" {
+    	access_origin('s)
+    	access_origin('s)
+    	goto c
+    }
+
+    c: "b = !move b" {
+    	goto d
+    }
+
+    d: "x = -copy x" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "Can't apply And")]
+fn logical_operators_apply_to_bools() {
+    let program = "
+        let x: i32;
+        let b: bool;
+        bb0: {
+            b = copy x && copy b;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+#[should_panic(expected = "Can't assign")]
+fn comparisons_produce_bools() {
+    let program = "
+        let x: i32;
+        bb0: {
+            x = copy x == 1;
+        }
+    ";
+    expect_facts(program);
+}
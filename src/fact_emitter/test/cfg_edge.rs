@@ -77,3 +77,252 @@ fn multiple_successor_blocks() {
     ]
     "###);
 }
+
+#[test]
+fn return_terminator() {
+    // The `return` terminator has its own node, which continues to the exit node
+    let program = "
+        let x: i32;
+        bb0: {
+            x = 1;
+            goto bb1, bb2;
+        }
+
+        bb1: {
+            return;
+        }
+
+        bb2: {
+            x = 2;
+            return;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).cfg_edge, @r###"
+    [
+        (
+            "a",
+            "b",
+        ),
+        (
+            "a",
+            "c",
+        ),
+        (
+            "b",
+            "e",
+        ),
+        (
+            "c",
+            "d",
+        ),
+        (
+            "d",
+            "e",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn call_terminator_unwinds() {
+    // The call terminator has its own node, which continues to both the normal and the cleanup
+    // successors. The destination is only written on the normal edge, at the next node.
+    let program = "
+        fn f<'a>(x: &'a i32) -> &'a i32;
+        let x: i32;
+        let r: &'r i32;
+        bb0: {
+            x = 1;
+            r = f(&'L_x x) -> [return: bb1, unwind: bb2];
+        }
+
+        bb1: {
+            return;
+        }
+
+        bb2 (cleanup): {
+            x = 2;
+        }
+    ";
+    insta::assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+
+    a: "x = 1" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "r = f(&'L_x x)" {
+    	clear_origin('L_x)
+    	clear_origin('f_a_b)
+    	introduce_subset('L_x, 'f_a_b)
+    	loan_issued_at('L_x, "x", shared)
+    	goto c e
+    }
+
+    c: "r = f(&'L_x x) -> return" {
+    	access_origin('f_a_b)
+    	clear_origin('r)
+    	introduce_subset('f_a_b, 'r)
+    	goto d
+    }
+
+    d: "return" {
+    	invalidate_origin('L_x)
+    	goto f
+    }
+
+    e: "x = 2" {
+    	cleanup_node()
+    	invalidate_origin('L_x)
+    	goto
+    }
+
+    f: "(pass)" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "only unwinding edges and cleanup blocks can continue to cleanup blocks")]
+fn only_unwinding_edges_reach_cleanup_blocks() {
+    let program = "
+        bb0: {
+            goto bb1;
+        }
+
+        bb1 (cleanup): {
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn structured_control_flow() {
+    // The scopes exited by a `break` end before continuing after the loop
+    let program = "
+        let b: bool;
+        if copy b {
+            let x: i32 = 1;
+        } else if !copy b {
+            b = !copy b;
+        }
+        loop {
+            let y: i32 = 2;
+            {
+                let z: i32 = 3;
+                if copy b {
+                    break;
+                }
+            }
+        }
+    ";
+    insta::assert_display_snapshot!(expect_facts(program), @r###"
+    a: "copy b" {
+    	goto b d
+    }
+
+    b: "let x: i32 = 1" {
+    	goto c
+    }
+
+    c: "}" {
+    	goto e
+    }
+
+    d: "!copy b" {
+    	goto f g
+    }
+
+    e: "(pass)" {
+    	goto h
+    }
+
+    f: "b = !copy b" {
+    	goto g
+    }
+
+    g: "(pass)" {
+    	goto e
+    }
+
+    h: "let y: i32 = 2" {
+    	goto i
+    }
+
+    i: "let z: i32 = 3" {
+    	goto j
+    }
+
+    j: "copy b" {
+    	goto l n
+    }
+
+    k: "return" {
+    	goto q
+    }
+
+    l: "break" {
+    	goto m
+    }
+
+    m: "break" {
+    	goto k
+    }
+
+    n: "}" {
+    	goto o
+    }
+
+    o: "}" {
+    	goto h
+    }
+
+    p: "(pass)" {
+    	goto n
+    }
+
+    q: "(pass)" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn conditions_are_bools() {
+    // The types of fields and calls are known from the declarations, but not the return types of
+    // undeclared functions.
+    let program = "
+        struct S { b: bool }
+        fn g() -> bool;
+        fn f(s: S, x: i32) -> () {
+            if copy s.b { }
+            while g() { }
+            if copy x < 1 { }
+            if undeclared() { }
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+#[should_panic(expected = "is not a bool")]
+fn field_conditions_are_bools() {
+    let program = "
+        struct S { f: i32 }
+        let s: S;
+        if copy s.f { }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+#[should_panic(expected = "is not a bool")]
+fn call_conditions_are_bools() {
+    let program = "
+        fn len() -> i32;
+        while len() { }
+    ";
+    expect_facts(program);
+}
@@ -1,4 +1,5 @@
 use super::*;
+use insta::assert_display_snapshot;
 
 #[test]
 fn assignments_to_references() {
@@ -116,3 +117,174 @@ fn mut_borrows_clear_their_origin() {
         Some(("'L_i".into(), "b".into()))
     );
 }
+
+#[test]
+fn static_origin_is_never_cleared() {
+    let facts = expect_facts(
+        "
+        static S: i32;
+        let x: &'static i32;
+
+        bb0: {
+            x = &'L_S S;
+        }
+    ",
+    );
+    assert_debug_snapshot!(facts.clear_origin, @r###"
+    [
+        (
+            "'L_S",
+            "a",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn writes_through_derefs_only_clear_the_target() {
+    // The reference used to reach the place is accessed, but not overwritten
+    let program = "
+        let x: i32;
+        let q: &'q i32;
+        let p: &'p mut &'q i32;
+
+        bb0: {
+            *p = &'L_x x;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    known_subset('q, 'p)
+
+    a: "*p = &'L_x x" {
+    	access_origin('p)
+    	clear_origin('q)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'q)
+    	loan_issued_at('L_x, "x", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn loans_through_derefs_of_overwritten_references_are_cleared() {
+    // Overwriting `temp` only re-points the reference: the memory borrowed by `'L_*temp` is not
+    // freed, and the loan is cleared, while the loan of `temp` itself is invalidated.
+    let program = "
+        let x: i32;
+        let y: i32;
+        let temp: &'temp i32;
+        let a: &'a i32;
+        let b: &'b &'temp i32;
+
+        bb0: {
+            temp = &'L_x x;
+            a = &'L_*temp *temp;
+            b = &'L_temp temp;
+            temp = &'L_y y;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_*temp)
+    mark_as_loan_origin('L_temp)
+    mark_as_loan_origin('L_y)
+    known_subset('temp, 'b)
+
+    a: "temp = &'L_x x" {
+    	invalidate_origin('L_temp)
+    	clear_origin('temp)
+    	clear_origin('L_*temp)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'temp)
+    	loan_issued_at('L_x, "x", shared)
+    	goto b
+    }
+
+    b: "a = &'L_*temp *temp" {
+    	clear_origin('a)
+    	clear_origin('L_*temp)
+    	introduce_subset('L_*temp, 'a)
+    	loan_issued_at('L_*temp, "*temp", shared)
+    	goto c
+    }
+
+    c: "b = &'L_temp temp" {
+    	clear_origin('b)
+    	clear_origin('temp)
+    	clear_origin('L_temp)
+    	introduce_subset('L_temp, 'b)
+    	loan_issued_at('L_temp, "temp", shared)
+    	goto d
+    }
+
+    d: "temp = &'L_y y" {
+    	invalidate_origin('L_temp)
+    	clear_origin('temp)
+    	clear_origin('L_*temp)
+    	clear_origin('L_y)
+    	introduce_subset('L_y, 'temp)
+    	loan_issued_at('L_y, "y", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn field_assignments_only_clear_the_field_origins() {
+    // Overwriting a field only clears the origins in its type: the borrows in the sibling fields,
+    // and in the origins shared with them, are still live.
+    let program = "
+        struct Pair<'a, 'b> {
+            first: &'a i32,
+            second: &'b i32,
+        }
+        struct Twice<'a> {
+            first: &'a i32,
+            second: &'a i32,
+        }
+
+        let x: i32;
+        let p: Pair<'p1, 'p2>;
+        let t: Twice<'t>;
+
+        bb0: {
+            p.first = &'L_x1 x;
+            t.first = &'L_x2 x;
+            t = Twice { first: &'L_x3 x, second: &'L_x4 x };
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x1)
+    mark_as_loan_origin('L_x2)
+    mark_as_loan_origin('L_x3)
+    mark_as_loan_origin('L_x4)
+
+    a: "p.first = &'L_x1 x" {
+    	clear_origin('p1)
+    	clear_origin('L_x1)
+    	introduce_subset('L_x1, 'p1)
+    	loan_issued_at('L_x1, "x", shared)
+    	goto b
+    }
+
+    b: "t.first = &'L_x2 x" {
+    	clear_origin('L_x2)
+    	introduce_subset('L_x2, 't)
+    	loan_issued_at('L_x2, "x", shared)
+    	goto c
+    }
+
+    c: "t = Twice { first: &'L_x3 x, second: &'L_x4 x }" {
+    	clear_origin('t)
+    	clear_origin('L_x3)
+    	clear_origin('L_x4)
+    	introduce_subset('L_x3, 't)
+    	introduce_subset('L_x4, 't)
+    	loan_issued_at('L_x3, "x", shared)
+    	loan_issued_at('L_x4, "x", shared)
+    	goto
+    }
+    "###);
+}
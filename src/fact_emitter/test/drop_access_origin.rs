@@ -0,0 +1,221 @@
+use super::*;
+use insta::assert_display_snapshot;
+
+#[test]
+fn drops_access_origins_observed_by_destructors() {
+    let program = "
+        struct Guard<'a>: Drop { r: &'a i32 }
+        struct Vec<#[may_dangle] T>: Drop { item0: T }
+        struct Pair<'a, 'b> { r: &'a i32, g: Guard<'b> }
+
+        let r: &'r i32;
+        let g: Guard<'g>;
+        let v: Vec<&'v i32>;
+        let vg: Vec<Guard<'vg>>;
+        let p: Pair<'p0, 'p1>;
+
+        bb0: {
+            drop(r);
+            drop(g);
+            drop(v);
+            drop(vg);
+            drop(p);
+            drop(p.g);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "drop(r)" {
+    	goto b
+    }
+
+    b: "drop(g)" {
+    	drop_access_origin('g)
+    	goto c
+    }
+
+    c: "drop(v)" {
+    	goto d
+    }
+
+    d: "drop(vg)" {
+    	drop_access_origin('vg)
+    	goto e
+    }
+
+    e: "drop(p)" {
+    	drop_access_origin('p1)
+    	goto f
+    }
+
+    f: "drop(p.g)" {
+    	access_origin('p0)
+    	access_origin('p1)
+    	drop_access_origin('p1)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn locals_are_dropped_when_returning() {
+    // Only the values with destructors access origins when dropped, and the return place is
+    // moved out instead of being dropped
+    let program = "
+        struct Guard<'a>: Drop { r: &'a i32 }
+        fn f<'a>(r: &'a i32) -> Guard<'a> {
+            let g: Guard<'g>;
+            let moved: Guard<'moved>;
+            let r2: &'r2 i32;
+            bb0: {
+                _0 = move moved;
+                return;
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "_0 = move moved" {
+    	access_origin('moved)
+    	introduce_subset('moved, 'a)
+    	goto b
+    }
+
+    b: "return" {
+    	drop_access_origin('g)
+    	goto c
+    }
+
+    c: "(pass)" {
+    	access_origin('a)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn locals_dropped_or_moved_out_on_every_path_are_not_dropped_again() {
+    // `g` is dropped explicitly, and `h` is moved out on both paths, but `m` is only moved out on
+    // one of them: it's still dropped when returning from the other.
+    let program = "
+        struct Guard<'a>: Drop { r: &'a i32 }
+        fn f(g: Guard<'g>, h: Guard<'h>, m: Guard<'m>) -> () {
+            let x: i32;
+            bb0: {
+                drop(g);
+                x = 2;
+                goto bb1, bb2;
+            }
+            bb1: {
+                consume(move h);
+                consume(move m);
+                goto bb3;
+            }
+            bb2: {
+                consume(move h);
+                goto bb3;
+            }
+            bb3: {
+                return;
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "drop(g)" {
+    	drop_access_origin('g)
+    	goto b
+    }
+
+    b: "x = 2" {
+    	goto c e
+    }
+
+    c: "consume(move h)" {
+    	access_origin('h)
+    	goto d
+    }
+
+    d: "consume(move m)" {
+    	access_origin('m)
+    	goto f
+    }
+
+    e: "consume(move h)" {
+    	access_origin('h)
+    	goto f
+    }
+
+    f: "return" {
+    	drop_access_origin('m)
+    	goto g
+    }
+
+    g: "(pass)" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn locals_assigned_after_being_moved_out_are_dropped() {
+    // Assigning to a moved out local initializes it again, but a call only initializes its
+    // destination when it returns, not when it unwinds.
+    let program = "
+        struct Guard<'a>: Drop { r: &'a i32 }
+        let g: Guard<'g>;
+        let h: Guard<'h>;
+        let s: Guard<'s>;
+        bb0: {
+            consume(move g);
+            consume(move h);
+            h = make(move s) -> [return: bb1, unwind: bb2];
+        }
+        bb1: {
+            g = move h;
+            return;
+        }
+        bb2 (cleanup): {
+            StorageDead(h);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "consume(move g)" {
+    	access_origin('g)
+    	goto b
+    }
+
+    b: "consume(move h)" {
+    	access_origin('h)
+    	goto c
+    }
+
+    c: "h = make(move s)" {
+    	access_origin('s)
+    	goto d g
+    }
+
+    d: "h = make(move s) -> return" {
+    	clear_origin('h)
+    	goto e
+    }
+
+    e: "g = move h" {
+    	access_origin('h)
+    	clear_origin('g)
+    	introduce_subset('h, 'g)
+    	goto f
+    }
+
+    f: "return" {
+    	drop_access_origin('g)
+    	goto h
+    }
+
+    g: "StorageDead(h)" {
+    	cleanup_node()
+    	goto
+    }
+
+    h: "(pass)" {
+    	goto
+    }
+    "###);
+}
@@ -1,5 +1,5 @@
 use super::*;
-use insta::assert_debug_snapshot;
+use insta::{assert_debug_snapshot, assert_display_snapshot};
 
 #[test]
 fn shared_reference_assignment() {
@@ -224,3 +224,746 @@ fn values_of_generic_types() {
     ]
     "###);
 }
+
+#[test]
+fn origin_parameters_of_generic_structs() {
+    let program = "
+        struct Ref<'r> { r: &'r i32 }
+        let a: Ref<'a>;
+        let b: Ref<'b>;
+        bb0: {
+            a = move b;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'b",
+            "'a",
+            "a",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn nested_parameters_of_generic_structs() {
+    // Origins nested in non-reference type parameters, and in references inside them
+    let program = "
+        struct Ref<'r> { r: &'r i32 }
+        let a: Vec<Vec<Ref<'a>>>;
+        let b: Vec<Vec<Ref<'b>>>;
+        let c: Vec<Option<&'c Ref<'d>>>;
+        let e: Vec<Option<&'e Ref<'f>>>;
+        bb0: {
+            a = move b;
+            c = move e;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'b",
+            "'a",
+            "a",
+        ),
+        (
+            "'e",
+            "'c",
+            "b",
+        ),
+        (
+            "'f",
+            "'d",
+            "b",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn declared_variance_of_generic_structs() {
+    // The variance of the struct's parameters is computed from their uses in its fields:
+    // `'a` is covariant, `'b` and `T` are invariant.
+    let program = "
+        struct Cell<'a, 'b, T> { r: &'a mut &'b i32, t: &'a mut T }
+        let a: Cell<'a, 'b, &'c i32>;
+        let d: Cell<'d, 'e, &'f i32>;
+        bb0: {
+            a = move d;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'d",
+            "'a",
+            "a",
+        ),
+        (
+            "'e",
+            "'b",
+            "a",
+        ),
+        (
+            "'b",
+            "'e",
+            "a",
+        ),
+        (
+            "'f",
+            "'c",
+            "a",
+        ),
+        (
+            "'c",
+            "'f",
+            "a",
+        ),
+    ]
+    "###);
+
+    // The variance of nested structs' parameters is composed with their parent's.
+    let program = "
+        struct Cell<'a, 'b> { r: &'a mut &'b i32 }
+        struct Wrapper<'a, 'b> { cell: Cell<'b, 'a> }
+        let a: Wrapper<'a, 'b>;
+        let c: Wrapper<'c, 'd>;
+        bb0: {
+            a = move c;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'c",
+            "'a",
+            "a",
+        ),
+        (
+            "'a",
+            "'c",
+            "a",
+        ),
+        (
+            "'d",
+            "'b",
+            "a",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "Can't relate structs")]
+fn different_structs_are_not_related() {
+    let program = "
+        struct A<'a> { a: &'a i32 }
+        struct B<'b> { b: &'b i32 }
+        let a: A<'x>;
+        let b: B<'y>;
+        bb0: {
+            a = move b;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn calls_to_generic_functions() {
+    // The generic type parameters are inferred from the arguments
+    let program = "
+        fn id<T>(x: T) -> T;
+        let x: i32;
+        let p: &'p i32;
+        let q: &'q i32;
+        bb0: {
+            p = &'L_x x;
+            q = id(move p);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'L_x",
+            "'p",
+            "a",
+        ),
+        (
+            "'p",
+            "'q",
+            "b",
+        ),
+    ]
+    "###);
+
+    // The generic origins are instantiated with fresh origins at each call, and the arguments flow
+    // into them
+    let program = "
+        fn pick<'a, T>(x: &'a T, y: &'a T) -> &'a T;
+        let x: i32;
+        let y: i32;
+        let p: &'p i32;
+        bb0: {
+            p = pick(&'L_x x, &'L_y y);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_y)
+
+    a: "p = pick(&'L_x x, &'L_y y)" {
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	clear_origin('L_y)
+    	clear_origin('pick_a_a)
+    	introduce_subset('L_x, 'pick_a_a)
+    	introduce_subset('L_y, 'pick_a_a)
+    	introduce_subset('pick_a_a, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	loan_issued_at('L_y, "y", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn higher_ranked_fn_ptrs() {
+    // A higher-ranked function pointer can be used where a concrete one is expected: its bound
+    // origins are instantiated with fresh origins
+    let program = "
+        let f: fn(&'f i32) -> &'f i32;
+        let g: for<'a> fn(&'a i32) -> &'a i32;
+        bb0: {
+            f = move g;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    a: "f = move g" {
+    	clear_origin('f)
+    	clear_origin('a_a)
+    	introduce_subset('f, 'a_a)
+    	introduce_subset('a_a, 'f)
+    	goto
+    }
+    "###);
+
+    // Higher-ranked function pointers with the same signature are related without subsets
+    let program = "
+        let f: for<'b> fn(&'b i32) -> &'b i32;
+        let g: for<'a> fn(&'a i32) -> &'a i32;
+        bb0: {
+            f = move g;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    []
+    "###);
+
+    // Calls through function pointers instantiate their bound origins, like generic functions
+    let program = "
+        let f: for<'a> fn(&'a i32) -> &'a i32;
+        let x: i32;
+        let p: &'p i32;
+        bb0: {
+            p = f(&'L_x x);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+
+    a: "p = f(&'L_x x)" {
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	clear_origin('f_a_a)
+    	introduce_subset('L_x, 'f_a_a)
+    	introduce_subset('f_a_a, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "one type is more general than the other")]
+fn concrete_fn_ptrs_are_not_higher_ranked() {
+    let program = "
+        let f: for<'a> fn(&'a i32) -> &'a i32;
+        let g: fn(&'g i32) -> &'g i32;
+        bb0: {
+            f = move g;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn declared_type_outlives_bounds_at_calls() {
+    // The origins in the type inferred for `T` must outlive the origin instantiating `'a`
+    let program = "
+        fn store<'a, T: 'a>(x: T) -> &'a ();
+        let x: i32;
+        let r: &'r ();
+        bb0: {
+            r = store(&'L_x x);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+
+    a: "r = store(&'L_x x)" {
+    	clear_origin('r)
+    	clear_origin('L_x)
+    	clear_origin('store_a_a)
+    	introduce_subset('L_x, 'store_a_a)
+    	introduce_subset('store_a_a, 'r)
+    	loan_issued_at('L_x, "x", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn trait_objects() {
+    // Unsizing coercions from concrete types: their origins flow into the trait object's bound
+    let program = "
+        struct Pair<'a, 'b> { a: &'a i32, b: &'b i32 }
+        let s: Pair<'s1, 's2>;
+        let p: &'p dyn Debug + 'd;
+        let b: Box<Pair<'b1, 'b2>>;
+        let c: Box<dyn Debug + 'c>;
+        bb0: {
+            p = &'L_s s;
+            c = move b;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'L_s",
+            "'p",
+            "a",
+        ),
+        (
+            "'s1",
+            "'d",
+            "a",
+        ),
+        (
+            "'s2",
+            "'d",
+            "a",
+        ),
+        (
+            "'b1",
+            "'c",
+            "b",
+        ),
+        (
+            "'b2",
+            "'c",
+            "b",
+        ),
+    ]
+    "###);
+
+    // Trait objects are related through their bound, according to the variance of their context
+    let program = "
+        let p: &'p dyn Debug + 'a;
+        let q: &'q dyn Debug + 'b;
+        let r: &'r mut dyn Debug + 'c;
+        let s: &'s mut dyn Debug + 'd;
+        bb0: {
+            p = copy q;
+            r = move s;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'q",
+            "'p",
+            "a",
+        ),
+        (
+            "'b",
+            "'a",
+            "a",
+        ),
+        (
+            "'s",
+            "'r",
+            "b",
+        ),
+        (
+            "'d",
+            "'c",
+            "b",
+        ),
+        (
+            "'c",
+            "'d",
+            "b",
+        ),
+    ]
+    "###);
+
+    // Unique references to concrete types can be coerced into unique references to trait objects
+    let program = "
+        struct Ref<'a> { a: &'a i32 }
+        let s: Ref<'s>;
+        let r: &'r mut dyn Debug + 'd;
+        bb0: {
+            r = &'L_s mut s;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'L_s",
+            "'r",
+            "a",
+        ),
+        (
+            "'s",
+            "'d",
+            "a",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "the position is Invariant, not covariant")]
+fn trait_objects_are_not_coerced_in_invariant_positions() {
+    let program = "
+        struct Ref<'a> { a: &'a i32 }
+        let s: &'x mut Ref<'s>;
+        let r: &'r mut &'y mut dyn Debug + 'd;
+        bb0: {
+            r = &'L_s mut s;
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn closures_capture_places() {
+    // The captures flow into the closure's fields, and calling the closure accesses them
+    let program = "
+        let x: i32;
+        let y: i32;
+        let z: &'z i32;
+        bb0: {
+            c = |&'L_x x, &'L_y mut y, move z|;
+            c();
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_y)
+
+    a: "c = |&'L_x x, &'L_y mut y, move z|" {
+    	access_origin('z)
+    	invalidate_origin('L_y)
+    	clear_origin('c_L_x)
+    	clear_origin('c_L_y)
+    	clear_origin('c_z)
+    	clear_origin('L_x)
+    	clear_origin('L_y)
+    	clear_origin('closure_c_new_c_L_x_a)
+    	clear_origin('closure_c_new_c_L_y_a)
+    	clear_origin('closure_c_new_c_z_a)
+    	introduce_subset('L_x, 'closure_c_new_c_L_x_a)
+    	introduce_subset('L_y, 'closure_c_new_c_L_y_a)
+    	introduce_subset('z, 'closure_c_new_c_z_a)
+    	introduce_subset('closure_c_new_c_L_x_a, 'c_L_x)
+    	introduce_subset('closure_c_new_c_L_y_a, 'c_L_y)
+    	introduce_subset('closure_c_new_c_z_a, 'c_z)
+    	loan_issued_at('L_x, "x", shared)
+    	loan_issued_at('L_y, "y", mutable)
+    	goto b
+    }
+
+    b: "c()" {
+    	access_origin('c_L_x)
+    	access_origin('c_L_y)
+    	access_origin('c_z)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn struct_literals() {
+    // Each field's value flows into the field's type, substituted with the LHS parameters
+    let program = "
+        struct Pair<'a, T> { a: &'a i32, b: T }
+        let x: i32;
+        let r: &'r i32;
+        let s: Pair<'s, &'t i32>;
+        let nested: Pair<'n0, Pair<'n1, &'n2 i32>>;
+
+        bb0: {
+            s = Pair { a: &'L1 x, b: move r };
+            nested = Pair { b: Pair { a: copy r, b: &'L2 x }, a: &'L3 x };
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).introduce_subset, @r###"
+    [
+        (
+            "'L1",
+            "'s",
+            "a",
+        ),
+        (
+            "'r",
+            "'t",
+            "a",
+        ),
+        (
+            "'r",
+            "'n1",
+            "b",
+        ),
+        (
+            "'L2",
+            "'n2",
+            "b",
+        ),
+        (
+            "'L3",
+            "'n0",
+            "b",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "must have a value for each field")]
+fn struct_literals_initialize_all_fields() {
+    let program = "
+        struct Pair<'a, 'b> { a: &'a i32, b: &'b i32 }
+        let x: i32;
+        let s: Pair<'s0, 's1>;
+
+        bb0: {
+            s = Pair { a: &'L x };
+        }
+    ";
+    expect_facts(program);
+}
+
+#[test]
+fn elided_origins() {
+    // Elided origins are named after their variable or the place they borrow
+    let program = "
+        let x: i32;
+        let p: &i32;
+        let q: &mut &i32;
+
+        bb0: {
+            p = &x;
+            q = &mut p;
+            p = &x;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_p)
+    mark_as_loan_origin('L_x2)
+    known_subset('q2, 'q)
+
+    a: "p = &x" {
+    	invalidate_origin('L_p)
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	goto b
+    }
+
+    b: "q = &mut p" {
+    	access_origin('p)
+    	invalidate_origin('L_p)
+    	clear_origin('q)
+    	clear_origin('q2)
+    	clear_origin('L_p)
+    	introduce_subset('L_p, 'q)
+    	introduce_subset('p, 'q2)
+    	introduce_subset('q2, 'p)
+    	loan_issued_at('L_p, "p", mutable)
+    	goto c
+    }
+
+    c: "p = &x" {
+    	invalidate_origin('L_p)
+    	clear_origin('p)
+    	clear_origin('L_x2)
+    	introduce_subset('L_x2, 'p)
+    	loan_issued_at('L_x2, "x", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn calls_with_elided_signature_origins() {
+    // The output reference of `first` is borrowed from its `self` argument
+    let program = "
+        struct Pair<'a> {
+            first: &'a i32,
+        }
+        fn first(self: &Pair<'_>, x: &i32) -> &i32;
+
+        let x: i32;
+        let p: Pair<'p>;
+        let r: &i32;
+
+        bb0: {
+            r = first(&p, &x);
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_p)
+    mark_as_loan_origin('L_x)
+
+    a: "r = first(&p, &x)" {
+    	clear_origin('r)
+    	clear_origin('L_p)
+    	clear_origin('L_x)
+    	clear_origin('first_1_a)
+    	clear_origin('first_2_a)
+    	clear_origin('first_3_a)
+    	introduce_subset('L_p, 'first_1_a)
+    	introduce_subset('p, 'first_2_a)
+    	introduce_subset('L_x, 'first_3_a)
+    	introduce_subset('first_1_a, 'r)
+    	loan_issued_at('L_p, "p", shared)
+    	loan_issued_at('L_x, "x", shared)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn calls_to_functions_with_bodies() {
+    // Each body is analyzed on its own, and its callers only rely on its signature
+    let program = "
+        fn id<'a>(x: &'a i32) -> &'a i32 {
+            _0 = copy x;
+        }
+
+        fn caller() -> () {
+            let x: i32 = 1;
+            let r: &i32 = id(&x);
+            x = 2;
+            copy *r;
+        }
+    ";
+    assert_display_snapshot!(expect_fn_facts(program, "id"), @r###"
+    a: "_0 = copy x" {
+    	access_origin('a)
+    	introduce_subset('a, 'a)
+    	goto b
+    }
+
+    b: "return" {
+    	goto c
+    }
+
+    c: "(pass)" {
+    	access_origin('a)
+    	goto
+    }
+    "###);
+    assert_display_snapshot!(expect_fn_facts(program, "caller"), @r###"
+    mark_as_loan_origin('L_x)
+
+    a: "let x: i32 = 1" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "let r: &i32 = id(&x)" {
+    	clear_origin('r)
+    	clear_origin('L_x)
+    	clear_origin('id_a_b)
+    	introduce_subset('L_x, 'id_a_b)
+    	introduce_subset('id_a_b, 'r)
+    	loan_issued_at('L_x, "x", shared)
+    	goto c
+    }
+
+    c: "x = 2" {
+    	invalidate_origin('L_x)
+    	goto d
+    }
+
+    d: "copy *r" {
+    	access_origin('r)
+    	goto e
+    }
+
+    e: "return" {
+    	invalidate_origin('L_x)
+    	goto f
+    }
+
+    f: "(pass)" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn calls_to_generic_functions_with_bodies() {
+    // The generic type parameters of a body's signature are resolved, in its variables too
+    let program = "
+        fn id<T>(x: T) -> T {
+            bb0: {
+                _0 = move x;
+                return;
+            }
+        }
+
+        fn caller() -> () {
+            let a: &'a i32;
+            let b: &'b i32;
+            bb0: {
+                a = id(move b);
+                return;
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_fn_facts(program, "id"), @r###"
+    a: "_0 = move x" {
+    	goto b
+    }
+
+    b: "return" {
+    	goto c
+    }
+
+    c: "(pass)" {
+    	goto
+    }
+    "###);
+    assert_display_snapshot!(expect_fn_facts(program, "caller"), @r###"
+    a: "a = id(move b)" {
+    	access_origin('b)
+    	clear_origin('a)
+    	introduce_subset('b, 'a)
+    	goto b
+    }
+
+    b: "return" {
+    	goto c
+    }
+
+    c: "(pass)" {
+    	goto
+    }
+    "###);
+}
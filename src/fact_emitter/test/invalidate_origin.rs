@@ -1,5 +1,5 @@
 use super::*;
-use insta::assert_debug_snapshot;
+use insta::{assert_debug_snapshot, assert_display_snapshot};
 
 #[test]
 fn assignment_to_non_references() {
@@ -67,3 +67,541 @@ fn mut_borrows_invalidate_loans() {
     ]
     "###);
 }
+
+#[test]
+fn mut_borrows_invalidate_loans_of_subplaces_and_ancestors() {
+    // Mutably borrowing `s` conflicts with the loans of its fields, and of the data its mutable
+    // references point to, but not with the loans of the data behind its shared references.
+    let program = "
+        struct S<'a, 'b> { f: i32, m: &'a mut i32, r: &'b i32 }
+        let s: S<'a, 'b>;
+        let x: &'x i32;
+        let y: &'y i32;
+        let z: &'z i32;
+        let t: &'t mut S<'a, 'b>;
+
+        bb0: {
+            x = &'L_f s.f;
+            y = &'L_m *s.m;
+            z = &'L_r *s.r;
+            t = &'L_s mut s;
+            use(copy x, copy y, copy z);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_f",
+            "d",
+        ),
+        (
+            "'L_m",
+            "d",
+        ),
+        (
+            "'L_s",
+            "d",
+        ),
+    ]
+    "###);
+
+    // Mutably borrowing the field `s.f` conflicts with the loan of `s`
+    let program = "
+        struct S { f: i32 }
+        let s: S;
+        let r: &'r S;
+        let m: &'m mut i32;
+
+        bb0: {
+            r = &'L_s s;
+            m = &'L_f mut s.f;
+            use(copy r);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_f",
+            "b",
+        ),
+        (
+            "'L_s",
+            "b",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn loans_of_statics_are_never_invalidated() {
+    let program = "
+        static S: i32;
+        const C: i32;
+        let x: &'x i32;
+        let y: &'y i32;
+
+        bb0: {
+            x = &'L_S S;
+            y = &'L_C C;
+            use(copy x, copy y);
+        }
+    ";
+    assert!(expect_facts(program).invalidate_origin.is_empty());
+}
+
+#[test]
+fn closure_captures_issue_loans() {
+    let program = "
+        let x: i32;
+        bb0: {
+            c = |&'L_x x|;
+            x = 1;
+            c();
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_x",
+            "b",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn returning_invalidates_loans_of_locals() {
+    // The locals are dead after returning, but the caller reads the return place: returning a
+    // reference to a local is an error, returning the reference to an argument is not.
+    let program = "
+        fn f<'a>(a: &'a i32) -> &'a i32 {
+            let x: i32;
+            let p: &'p i32;
+            bb0: {
+                x = 1;
+                p = &'L_x x;
+                p = &'L_a *a;
+                _0 = copy p;
+                return;
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_a)
+
+    a: "x = 1" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "p = &'L_x x" {
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	goto c
+    }
+
+    c: "p = &'L_a *a" {
+    	clear_origin('p)
+    	clear_origin('L_a)
+    	introduce_subset('L_a, 'p)
+    	loan_issued_at('L_a, "*a", shared)
+    	goto d
+    }
+
+    d: "_0 = copy p" {
+    	access_origin('p)
+    	introduce_subset('p, 'a)
+    	goto e
+    }
+
+    e: "return" {
+    	invalidate_origin('L_x)
+    	goto f
+    }
+
+    f: "(pass)" {
+    	access_origin('a)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn borrowed_temporaries_are_dead_at_the_end_of_their_statement() {
+    // Only constants are promoted to `'static` data: the other borrowed rvalues are stored in
+    // temporaries, whose loans are invalidated at the end of their statement
+    let program = "
+        fn make<'m>(r: &'m i32) -> Vec<&'m i32>;
+        fn f<'a>(x: &'a i32) -> &'a i32 {
+            let promoted: &'promoted i32;
+            let m: &'m mut i32;
+            let v: &'v Vec<&'data i32>;
+            bb0: {
+                promoted = &'L_promoted0 22;
+                m = &'L_tmp1 mut 22;
+                v = &'L_tmp2 make(copy x);
+                use(copy v);
+                _0 = copy promoted;
+                return;
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_promoted0)
+    mark_as_loan_origin('L_tmp1)
+    mark_as_loan_origin('L_tmp2)
+    known_subset('data, 'v)
+
+    a: "promoted = &'L_promoted0 22" {
+    	clear_origin('promoted)
+    	clear_origin('L_promoted0)
+    	introduce_subset('L_promoted0, 'promoted)
+    	loan_issued_at('L_promoted0, "promoted0", shared)
+    	goto b
+    }
+
+    b: "m = &'L_tmp1 mut 22" {
+    	invalidate_origin('L_tmp1)
+    	clear_origin('m)
+    	clear_origin('L_tmp1)
+    	introduce_subset('L_tmp1, 'm)
+    	loan_issued_at('L_tmp1, "tmp1", mutable)
+    	goto c
+    }
+
+    c: "StorageDead(tmp1)" {
+    	invalidate_origin('L_tmp1)
+    	goto d
+    }
+
+    d: "v = &'L_tmp2 make(copy x)" {
+    	access_origin('a)
+    	clear_origin('v)
+    	clear_origin('data)
+    	clear_origin('make_m_d)
+    	clear_origin('L_tmp2)
+    	introduce_subset('a, 'make_m_d)
+    	introduce_subset('L_tmp2, 'v)
+    	introduce_subset('make_m_d, 'data)
+    	loan_issued_at('L_tmp2, "tmp2", shared)
+    	goto e
+    }
+
+    e: "StorageDead(tmp2)" {
+    	drop_access_origin('make_m_d)
+    	invalidate_origin('L_tmp2)
+    	goto f
+    }
+
+    f: "use(copy v)" {
+    	access_origin('v)
+    	access_origin('data)
+    	goto g
+    }
+
+    g: "_0 = copy promoted" {
+    	access_origin('promoted)
+    	introduce_subset('promoted, 'a)
+    	goto h
+    }
+
+    h: "return" {
+    	goto i
+    }
+
+    i: "(pass)" {
+    	access_origin('a)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn temporaries_borrowed_by_let_initializers_are_extended() {
+    // The temporary lives as long as `r`, unlike the call argument's, which is dead at the end
+    // of the `let` statement
+    let program = "
+        fn f() -> i32;
+        fn g<'g>(x: &'g i32) -> i32;
+        fn h() -> () {
+            {
+                let r: &'r i32 = &'L_tmp0 f();
+                let y: i32 = g(&'L_tmp1 f());
+                use(copy r);
+            }
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_tmp0)
+    mark_as_loan_origin('L_tmp1)
+
+    a: "let r: &'r i32 = &'L_tmp0 f()" {
+    	clear_origin('r)
+    	clear_origin('L_tmp0)
+    	introduce_subset('L_tmp0, 'r)
+    	loan_issued_at('L_tmp0, "tmp0", shared)
+    	goto b
+    }
+
+    b: "let y: i32 = g(&'L_tmp1 f())" {
+    	clear_origin('L_tmp1)
+    	clear_origin('g_g_b)
+    	introduce_subset('L_tmp1, 'g_g_b)
+    	loan_issued_at('L_tmp1, "tmp1", shared)
+    	goto c
+    }
+
+    c: "StorageDead(tmp1)" {
+    	invalidate_origin('L_tmp1)
+    	goto d
+    }
+
+    d: "use(copy r)" {
+    	access_origin('r)
+    	goto e
+    }
+
+    e: "}" {
+    	goto f
+    }
+
+    f: "}" {
+    	goto g
+    }
+
+    g: "StorageDead(tmp0)" {
+    	invalidate_origin('L_tmp0)
+    	goto h
+    }
+
+    h: "return" {
+    	goto i
+    }
+
+    i: "(pass)" {
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn writes_through_derefs_invalidate_loans_of_subplaces() {
+    let program = "
+        struct S { f: i32 }
+        let s: S;
+        let p: &'p mut S;
+        let r: &'r i32;
+        let r2: &'r2 S;
+
+        bb0: {
+            r = &'L_f (*p).f;
+            r2 = &'L_p *p;
+            *p = move s;
+        }
+    ";
+    assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_f)
+    mark_as_loan_origin('L_p)
+
+    a: "r = &'L_f (*p).f" {
+    	clear_origin('r)
+    	clear_origin('L_f)
+    	introduce_subset('L_f, 'r)
+    	loan_issued_at('L_f, "(*p).f", shared)
+    	goto b
+    }
+
+    b: "r2 = &'L_p *p" {
+    	clear_origin('r2)
+    	clear_origin('L_p)
+    	introduce_subset('L_p, 'r2)
+    	loan_issued_at('L_p, "*p", shared)
+    	goto c
+    }
+
+    c: "*p = move s" {
+    	access_origin('p)
+    	invalidate_origin('L_f)
+    	invalidate_origin('L_p)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn writes_invalidate_loans_of_ancestors() {
+    // Assigning to a field overwrites a part of the struct
+    let program = "
+        struct S { f: i32 }
+        let s: S;
+        let r: &'r S;
+
+        bb0: {
+            r = &'L_s s;
+            s.f = 3;
+            use(copy r);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_s",
+            "b",
+        ),
+    ]
+    "###);
+
+    // Assigning through a reference modifies the data it points to
+    let program = "
+        let p: &'p mut i32;
+        let r: &'r &'p mut i32;
+
+        bb0: {
+            r = &'L_p p;
+            *p = 3;
+            use(copy r);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_p",
+            "b",
+        ),
+    ]
+    "###);
+
+    // But the shared reference the write goes through is only read: the loan of `q` is not
+    // invalidated, unlike the loan of the mutable reference `*q` it points to
+    let program = "
+        let q: &'q &'m mut i32;
+        let r: &'r &'q &'m mut i32;
+        let s: &'s &'m mut i32;
+
+        bb0: {
+            r = &'L_q q;
+            s = &'L_deref_q *q;
+            **q = 3;
+            use(copy r, copy s);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_deref_q",
+            "c",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn drops_invalidate_loans_of_subplaces() {
+    // Dropping `s` frees the memory of its field, but not the data `s.p` points to
+    let program = "
+        struct S<'a> { f: i32, p: &'a i32 }
+        let x: i32;
+        let s: S<'s>;
+        let r: &'r i32;
+        let q: &'q i32;
+
+        bb0: {
+            s = S { f: 1, p: &'L_x x };
+            r = &'L_f s.f;
+            q = &'L_p *s.p;
+            drop(s);
+            use(copy r);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).invalidate_origin, @r###"
+    [
+        (
+            "'L_f",
+            "a",
+        ),
+        (
+            "'L_f",
+            "d",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn locals_are_dead_at_the_end_of_their_scope() {
+    // The loan of `x` is invalidated when `x` goes out of scope, but not the ones of the
+    // references `r` points to
+    let program = "
+        let r: &i32;
+        let s: &&i32;
+        {
+            let x: i32 = 1;
+            r = &x;
+            let t: &i32 = copy r;
+            s = &t;
+        }
+        copy *r;
+    ";
+    insta::assert_display_snapshot!(expect_facts(program), @r###"
+    mark_as_loan_origin('L_x)
+    mark_as_loan_origin('L_t)
+    known_subset('s2, 's)
+
+    a: "let x: i32 = 1" {
+    	invalidate_origin('L_x)
+    	goto b
+    }
+
+    b: "r = &x" {
+    	clear_origin('r)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'r)
+    	loan_issued_at('L_x, "x", shared)
+    	goto c
+    }
+
+    c: "let t: &i32 = copy r" {
+    	access_origin('r)
+    	invalidate_origin('L_t)
+    	clear_origin('t)
+    	introduce_subset('r, 't)
+    	goto d
+    }
+
+    d: "s = &t" {
+    	clear_origin('s)
+    	clear_origin('s2)
+    	clear_origin('L_t)
+    	introduce_subset('L_t, 's)
+    	introduce_subset('t, 's2)
+    	loan_issued_at('L_t, "t", shared)
+    	goto e
+    }
+
+    e: "}" {
+    	invalidate_origin('L_t)
+    	goto f
+    }
+
+    f: "}" {
+    	invalidate_origin('L_x)
+    	goto g
+    }
+
+    g: "copy *r" {
+    	access_origin('r)
+    	goto h
+    }
+
+    h: "return" {
+    	goto i
+    }
+
+    i: "(pass)" {
+    	goto
+    }
+    "###);
+}
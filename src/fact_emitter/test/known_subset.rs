@@ -0,0 +1,113 @@
+use super::*;
+use insta::assert_debug_snapshot;
+
+#[test]
+fn implied_bounds_of_references() {
+    let program = "
+        let a: &'a i32;
+        let b: &'b &'c i32;
+        let d: &'d mut &'e &'f i32;
+    ";
+    assert_debug_snapshot!(expect_facts(program).known_subset, @r###"
+    [
+        (
+            "'c",
+            "'b",
+        ),
+        (
+            "'e",
+            "'d",
+        ),
+        (
+            "'f",
+            "'d",
+        ),
+        (
+            "'f",
+            "'e",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn implied_bounds_of_struct_parameters() {
+    let program = "
+        struct Ref<'a, T> { r: &'a T }
+        struct Pair<'a, 'b> { p: Ref<'a, Ref<'b, i32>> }
+        let r: Ref<'r, &'s i32>;
+        let p: Pair<'x, 'y>;
+        let v: Vec<&'v &'w i32>;
+    ";
+    assert_debug_snapshot!(expect_facts(program).known_subset, @r###"
+    [
+        (
+            "'s",
+            "'r",
+        ),
+        (
+            "'y",
+            "'x",
+        ),
+        (
+            "'w",
+            "'v",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn implied_bounds_of_recursive_structs() {
+    let program = "
+        struct List<'a> { next: &'a List<'a>, data: &'a &'a i32 }
+        let l: List<'l>;
+    ";
+    assert!(expect_facts(program).known_subset.is_empty());
+}
+
+#[test]
+fn implied_bounds_of_declared_type_outlives_bounds() {
+    // The struct has no reference to `T` in its fields, but declares that it outlives `'a` and
+    // `'b`
+    let program = "
+        struct Bounded<'a, 'b, T: 'a + 'b> { t: T }
+        let x: Bounded<'x, 'y, Pair<'p, 'q>>;
+    ";
+    assert_debug_snapshot!(expect_facts(program).known_subset, @r###"
+    [
+        (
+            "'p",
+            "'x",
+        ),
+        (
+            "'q",
+            "'x",
+        ),
+        (
+            "'p",
+            "'y",
+        ),
+        (
+            "'q",
+            "'y",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn implied_bounds_of_trait_objects() {
+    let program = "
+        let p: &'p dyn Debug + 'd;
+        let b: Box<dyn Debug + 'b>;
+    ";
+    assert_debug_snapshot!(expect_facts(program).known_subset, @r###"
+    [
+        (
+            "'d",
+            "'p",
+        ),
+    ]
+    "###);
+}
@@ -0,0 +1,102 @@
+use super::*;
+use insta::assert_debug_snapshot;
+
+#[test]
+fn loans_of_places() {
+    // Each loan is issued where its borrow is evaluated, with the place it borrows and its mode
+    let program = "
+        struct S<'a> { f: &'a i32 }
+        let x: i32;
+        let s: S<'s>;
+        let p: &'p S<'ps>;
+        let r: &'r i32;
+        let m: &'m mut i32;
+        bb0: {
+            r = &'L_x x;
+            m = &'L_mut_x mut x;
+            p = &'L_s s;
+            r = &'L_f (*p).f;
+            r = &'L_deref *r;
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).loan_issued_at, @r###"
+    [
+        (
+            "'L_x",
+            "x",
+            Shared,
+            "a",
+        ),
+        (
+            "'L_mut_x",
+            "x",
+            Mutable,
+            "b",
+        ),
+        (
+            "'L_s",
+            "s",
+            Shared,
+            "c",
+        ),
+        (
+            "'L_f",
+            "(*p).f",
+            Shared,
+            "d",
+        ),
+        (
+            "'L_deref",
+            "*r",
+            Shared,
+            "e",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn loans_of_call_arguments() {
+    // The loans of a call's arguments are issued at the call's node, including the loans of
+    // statics, which are never invalidated
+    let program = "
+        fn f<'a, 'b>(a: &'a i32, b: &'b mut i32) -> ();
+        static S: i32;
+        let x: i32;
+        let y: i32;
+        bb0: {
+            f(&'L_x x, &'L_y mut y) -> [return: bb1];
+        }
+        bb1: {
+            f(&'L_S S, &'L_x2 mut x);
+        }
+    ";
+    assert_debug_snapshot!(expect_facts(program).loan_issued_at, @r###"
+    [
+        (
+            "'L_x",
+            "x",
+            Shared,
+            "a",
+        ),
+        (
+            "'L_y",
+            "y",
+            Mutable,
+            "a",
+        ),
+        (
+            "'L_S",
+            "S",
+            Shared,
+            "c",
+        ),
+        (
+            "'L_x2",
+            "x",
+            Mutable,
+            "c",
+        ),
+    ]
+    "###);
+}
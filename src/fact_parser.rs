@@ -1,6 +1,8 @@
 //! Parser for "fact files", a compact way to represent facts.
 //!
 //! ```notrust
+//! File       := Program | Function+
+//! Function   := fn Ident { Program }
 //! Program    := Fact* Statement,
 //! Statement  := Ident: String { Fact* goto Ident* }
 //! Fact       := Ident ( Symbol, )*
@@ -32,23 +34,38 @@ pub struct Fact {
 
 peg::parser! {
     grammar fact_parser() for str {
-        pub rule program() -> Program = comment()* _ g:fact()**__ _ n:statement()**__ _ {
+        // The facts of a single function, or of multiple named functions, which are analyzed
+        // independently.
+        pub rule file() -> Vec<(Option<String>, Program)> = (
+            functions:function()++_ _ {
+                functions.into_iter().map(|(name, program)| (Some(name), program)).collect()
+            } /
+            program:program() { vec![(None, program)] }
+        )
+
+        rule function() -> (String, Program) = (
+            comment()* _ "fn" __ name:ident() _ "{" _ program:program() _ "}" {
+                (name, program)
+            }
+        )
+
+        rule program() -> Program = comment()* _ g:fact()**__ _ n:statement()**__ _ {
             Program {
                 global_facts: g,
                  statements: n
             }
         }
 
-        rule _ = quiet!{[' ' | '\n']*}
-        rule __ = quiet!{[' ' | '\n']+}
+        rule _ = quiet!{[' ' | '\t' | '\n']*}
+        rule __ = quiet!{[' ' | '\t' | '\n']+}
 
         rule comment() -> () = _ "//" [^'\n']* "\n" { () }
 
-        rule statement() -> Statement = name:ident() _ ":" _ text:string() _ "{" _ facts:fact()**__ _ "goto" _ successors:ident()**__ _ "}" {
+        rule statement() -> Statement = name:ident() _ ":" _ text:string() _ "{" _ facts:fact()**__ _ comment()* _ "goto" _ successors:ident()**__ _ "}" {
             Statement { name, text, facts, successors }
         }
 
-        rule fact() -> Fact = comment()* _ name:ident() _ "(" _ arguments:symbol()**comma() _ ")" {
+        pub rule fact() -> Fact = comment()* _ name:ident() _ "(" _ arguments:symbol()**comma() _ ")" {
             Fact { name, arguments }
         }
 
@@ -66,13 +83,37 @@ peg::parser! {
     }
 }
 
-fn parse_facts(input: &str) -> eyre::Result<Program> {
-    Ok(fact_parser::program(input)?)
+pub(crate) fn parse_facts(input: &str) -> eyre::Result<Vec<(Option<String>, Program)>> {
+    Ok(fact_parser::file(input)?)
 }
 
-pub fn generate_facts(input: &str, output_path: &Path) -> eyre::Result<()> {
-    let program = parse_facts(input).wrap_err("failed to parse input")?;
-    let facts = collect_facts(&program)?;
+/// Parses a single fact, e.g. `invalidated_origin_accessed('L_x, e)`.
+pub(crate) fn parse_fact(input: &str) -> eyre::Result<Fact> {
+    Ok(fact_parser::fact(input.trim())?)
+}
+
+/// Writes the facts of each function in the input, and returns the functions' names. The facts of
+/// a single unnamed function are written to `output_path`, and the ones of the named functions to
+/// their own `output_path/<name>` directory.
+pub fn generate_facts(input: &str, output_path: &Path) -> eyre::Result<Vec<Option<String>>> {
+    let functions = parse_facts(input).wrap_err("failed to parse input")?;
+
+    let mut names = Vec::new();
+    for (name, program) in functions {
+        let function_path = match &name {
+            Some(name) => output_path.join(name),
+            None => output_path.to_path_buf(),
+        };
+        std::fs::create_dir_all(&function_path)?;
+        write_facts(&program, &function_path)?;
+        names.push(name);
+    }
+
+    Ok(names)
+}
+
+fn write_facts(program: &Program, output_path: &Path) -> eyre::Result<()> {
+    let facts = collect_facts(program)?;
 
     for (fact_name, fact_rows) in facts.into_iter() {
         let fact_path = output_path.join(fact_name).with_extension("facts");
@@ -87,17 +128,46 @@ pub fn generate_facts(input: &str, output_path: &Path) -> eyre::Result<()> {
     Ok(())
 }
 
-const EXPECTED_GLOBAL_FACT_NAMES: &[&str] = &["mark_as_loan_origin"];
+/// Reads the input facts written to the `facts_path` directory, e.g. by `generate_facts`. The
+/// missing relations are empty.
+pub(crate) fn read_facts(facts_path: &Path) -> eyre::Result<HashMap<String, Vec<Vec<String>>>> {
+    let mut facts = HashMap::new();
+
+    let fact_names = EXPECTED_GLOBAL_FACT_NAMES
+        .iter()
+        .chain(EXPECTED_LOCAL_FACT_NAMES.iter())
+        .chain(Some(&"node_text"));
+    for fact_name in fact_names {
+        let fact_path = facts_path.join(fact_name).with_extension("facts");
+        let fact_rows = if fact_path.exists() {
+            std::fs::read_to_string(&fact_path)
+                .wrap_err_with(|| format!("failed to read facts from `{}`", fact_path.display()))?
+                .lines()
+                .map(|line| line.split('\t').map(str::to_string).collect())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        facts.insert(fact_name.to_string(), fact_rows);
+    }
+
+    Ok(facts)
+}
+
+const EXPECTED_GLOBAL_FACT_NAMES: &[&str] = &["known_subset", "mark_as_loan_origin"];
 const EXPECTED_LOCAL_FACT_NAMES: &[&str] = &[
+    "cleanup_node",
     "access_origin",
     "cfg_edge",
+    "drop_access_origin",
     "clear_origin",
     "introduce_subset",
     "invalidate_origin",
+    "loan_issued_at",
 ];
 
 /// Maps a program into a set of facts:
-fn collect_facts(program: &Program) -> eyre::Result<HashMap<String, Vec<Vec<String>>>> {
+pub(crate) fn collect_facts(program: &Program) -> eyre::Result<HashMap<String, Vec<Vec<String>>>> {
     let mut facts = HashMap::new();
 
     for expected in EXPECTED_GLOBAL_FACT_NAMES
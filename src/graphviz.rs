@@ -1,8 +1,17 @@
 use glob::glob;
 use html_escape;
 use itertools::Itertools;
-use std::{collections::HashMap, fs, io::Write, path::Path, process::Command};
-const IMPORTANT_RELATIONS: &[&str] = &["invalidated_origin_accessed"];
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Write,
+    path::Path,
+    process::Command,
+};
+const IMPORTANT_RELATIONS: &[&str] = &[
+    "invalidated_origin_accessed",
+    "invalidated_origin_accessed_while_unwinding",
+];
 
 #[derive(Debug, Default)]
 struct Data {
@@ -10,6 +19,7 @@ struct Data {
     pub(crate) input_per_node: HashMap<String, Vec<(String, Importance)>>,
     pub(crate) node_predecessors: HashMap<String, Vec<String>>,
     pub(crate) output_per_node: HashMap<String, Vec<(String, Importance)>>,
+    pub(crate) cleanup_nodes: HashSet<String>,
 }
 
 impl Data {
@@ -48,16 +58,11 @@ impl From<bool> for Importance {
     }
 }
 
-pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
-    // Resolve name-only output paths
-    let output_file_path = if output_file_path.components().count() == 1 {
-        fact_directory.join(output_file_path)
-    } else {
-        output_file_path.to_path_buf()
-    };
-    let input_facts_directory = fact_directory.join("facts");
-    let output_facts_directory = fact_directory.join("output");
-
+pub(crate) fn create_graph(
+    input_facts_directory: &Path,
+    output_facts_directory: &Path,
+    output_file_path: &Path,
+) {
     // Process input facts: load fact files from the provided input facts directory, and store the
     // atoms (without locations) in the files as facts at each node in the CFG
     let mut data = Data::new();
@@ -101,6 +106,23 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
                         .unwrap()
                         .push(p.to_string());
                 }
+                "cleanup_node" => {
+                    // The nodes only running while unwinding, drawn with a dashed border
+                    let node = atoms.next_back().unwrap();
+                    data.cleanup_nodes.insert(node.to_string());
+                }
+                "loan_issued_at" => {
+                    // The loans issued at the node, described in words
+                    let (origin, place, mode, node) = atoms.collect_tuple().unwrap();
+                    let description = format!("{}: {} borrow of `{}`", origin, mode, place);
+                    data.input_per_node
+                        .entry(node.to_string())
+                        .or_default()
+                        .push((
+                            html_escape::encode_text(&description).to_string(),
+                            false.into(),
+                        ));
+                }
                 _ => {
                     // Actual facts happening at the node
                     let node = atoms.next_back().unwrap();
@@ -118,13 +140,16 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
     }
 
     // Process output facts, in a similar fashion as the input facts: the relations are also
-    // suffixed by the node.
+    // suffixed by the node. The loans are output as they were input, and already described.
     let pattern = output_facts_directory.join("*.csv");
     for path in glob(pattern.to_str().expect("output path was not UTF-8"))
         .unwrap()
         .filter_map(Result::ok)
     {
         let relation = path.file_stem().unwrap().to_str().unwrap();
+        if relation == "loan_issued_at" {
+            continue;
+        }
         let facts = fs::read_to_string(&path).expect(&format!(
             "could not read relation file '{}'",
             path.to_string_lossy()
@@ -177,13 +202,18 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
             }));
         }
         let lines: String = Itertools::intersperse(rows.iter().map(|s| s.as_str()), "\n").collect();
+        let style = if data.cleanup_nodes.contains(node) {
+            r#" style = "dashed""#
+        } else {
+            ""
+        };
         output_dot += &format!(
             r#"    {} [ label = <<table border="0">
     <tr><td>{}</td></tr>
     <tr><td>-------------------</td></tr>
 {}
-    </table>> ]"#,
-            node, node_text, lines
+    </table>>{} ]"#,
+            node, node_text, lines, style
         );
 
         if let Some(preds) = data.node_predecessors.get(node) {
@@ -200,7 +230,7 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
         .write(true)
         .create(true)
         .truncate(true)
-        .open(&output_file_path)
+        .open(output_file_path)
         .expect("could not open output file");
     output_file
         .write(output_dot.as_bytes())
@@ -1,14 +1,19 @@
 mod ast;
 mod ast_parser;
+mod elision;
 mod fact_emitter;
 mod fact_parser;
 mod graphviz;
+mod lowering;
+mod slicing;
 mod span;
+mod subst;
 
 use std::{path::PathBuf, process::Command};
 
 use eyre::Context;
 pub use fact_parser::generate_facts;
+pub use slicing::slice_facts;
 
 pub fn test_harness(dir_name: &str) -> eyre::Result<()> {
     // let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
@@ -20,28 +25,54 @@ pub fn test_harness(dir_name: &str) -> eyre::Result<()> {
     let data = std::fs::read_to_string(input_path)?;
 
     std::fs::create_dir_all(&facts_path)?;
-    generate_facts(&data, &facts_path)?;
+    let functions = generate_facts(&data, &facts_path)?;
 
     let output_path = path.join("output");
     std::fs::create_dir_all(&output_path)?;
 
-    let status = Command::new("souffle")
-        .args(&[
-            manifest_dir.join("src/polonius.dl").display().to_string(),
-            "-F".to_string(),
-            facts_path.display().to_string(),
-            "-D".to_string(),
-            output_path.display().to_string(),
-        ])
-        .status()
-        .wrap_err("failed to run soufflé")?;
+    // Each function is analyzed on its own. When there are named functions, their results are
+    // gathered in a single file, where each row starts with the function's name.
+    let mut named_results = String::new();
+    for function in &functions {
+        let (facts_path, output_path) = match function {
+            Some(name) => (facts_path.join(name), output_path.join(name)),
+            None => (facts_path.clone(), output_path.clone()),
+        };
+        std::fs::create_dir_all(&output_path)?;
+
+        let status = Command::new("souffle")
+            .args(&[
+                manifest_dir.join("src/polonius.dl").display().to_string(),
+                "-F".to_string(),
+                facts_path.display().to_string(),
+                "-D".to_string(),
+                output_path.display().to_string(),
+            ])
+            .status()
+            .wrap_err("failed to run soufflé")?;
 
-    if !status.success() {
-        return Err(eyre::eyre!("failed to run soufflé"));
+        if !status.success() {
+            return Err(eyre::eyre!("failed to run soufflé"));
+        }
+
+        let dot_path = output_path.join("graph.dot");
+        graphviz::create_graph(&facts_path, &output_path, &dot_path);
+
+        if let Some(name) = function {
+            let results =
+                std::fs::read_to_string(output_path.join("invalidated_origin_accessed.csv"))?;
+            for row in results.lines() {
+                named_results.push_str(&format!("{}\t{}\n", name, row));
+            }
+        }
     }
 
-    let dot_path = output_path.join("graph.dot");
-    graphviz::create_graph(path.as_path(), dot_path.as_path());
+    if functions.iter().any(Option::is_some) {
+        std::fs::write(
+            output_path.join("invalidated_origin_accessed.csv"),
+            named_results,
+        )?;
+    }
 
     if std::env::var("BLESS").is_ok() {
         let status = Command::new("cp")
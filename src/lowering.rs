@@ -0,0 +1,262 @@
+//! Lowering of the structured syntax into basic blocks.
+//!
+//! Instead of basic blocks, a body can be written with Rust-like control flow:
+//!
+//! ```notrust
+//! let x: i32 = 0;
+//! while copy x < 10 {
+//!     let r: &i32 = &x;
+//!     if copy *r == 5 { break; } else { x = copy x + 1; }
+//! }
+//! ```
+//!
+//! The locals are declared by `let` statements, and live until the end of their `{ .. }` scope,
+//! where a `StorageDead` is inserted for each of them, in reverse order of declaration. The locals
+//! of the function's outermost scope live until it returns. A `break` ends the scopes it exits,
+//! and continues after its loop. The temporaries borrowed by a `let` initializer, as in
+//! `let r: &i32 = &f();`, are extended to live as long as the local.
+//!
+//! Blocks are named `bb0`, `bb1`, etc, in the order they're created. The lowered statements keep
+//! the spans of their source: the `let` statement for its initialization, the condition for its
+//! evaluation, and the closing brace of the scope, or the `break`, for the `StorageDead`s.
+
+use crate::ast::*;
+use crate::span::{Span, Spanned as Sp, WithSpan};
+
+#[derive(Debug)]
+pub enum Stmt {
+    /// `let x: T;` or `let x: T = expr;`, whose initialization is an assignment.
+    Let {
+        decl: VariableDecl,
+        init: Option<Sp<Statement>>,
+    },
+
+    /// The statements of the basic block syntax: `place = expr;`, `expr;`, `drop(place);`, etc.
+    Statement(Sp<Statement>),
+
+    /// `if cond { .. } else { .. }`: the condition is evaluated as an expression statement.
+    If {
+        cond: Sp<Statement>,
+        then_block: Block,
+        else_block: Option<Block>,
+    },
+
+    /// `while cond { .. }`
+    While { cond: Sp<Statement>, body: Block },
+
+    /// `loop { .. }`, which only exits with a `break`.
+    Loop { body: Block },
+
+    /// `break;`, which exits the innermost loop.
+    Break(Span),
+
+    /// A `{ .. }` scope.
+    Block(Block),
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+
+    /// The span of the closing brace, where the scope's locals die.
+    pub end: Span,
+}
+
+/// Lowers the statements of a structured body into the locals they declare, and the basic blocks
+/// of their CFG. The first block is the entry block, and the body returns after its last
+/// statement.
+pub fn lower_body(stmts: Vec<Stmt>) -> Result<(Vec<VariableDecl>, Vec<BasicBlock>), &'static str> {
+    let mut lowering = Lowering::default();
+    lowering.scopes.push(Vec::new());
+    lowering.current = lowering.new_block();
+    for stmt in stmts {
+        lowering.lower_stmt(stmt)?;
+    }
+    lowering.blocks[lowering.current].terminator = Terminator::Return;
+    Ok((lowering.variables, lowering.blocks))
+}
+
+#[derive(Default)]
+struct Lowering {
+    variables: Vec<VariableDecl>,
+    blocks: Vec<BasicBlock>,
+
+    /// The index of the block where the statements are lowered.
+    current: usize,
+
+    /// The locals declared in each of the enclosing scopes, from the outermost one.
+    scopes: Vec<Vec<Name>>,
+
+    /// The enclosing loops, from the outermost one: the number of scopes around the loop, and the
+    /// block continuing after it.
+    loops: Vec<(usize, usize)>,
+}
+
+impl Lowering {
+    fn new_block(&mut self) -> usize {
+        let idx = self.blocks.len();
+        self.blocks.push(BasicBlock {
+            name: format!("bb{}", idx),
+            is_cleanup: false,
+            statements: Vec::new(),
+            terminator: Terminator::Goto(Vec::new()),
+        });
+        idx
+    }
+
+    // Terminates the current block with a `goto` to the `successors`.
+    fn goto(&mut self, successors: &[usize]) {
+        let successors = successors
+            .iter()
+            .map(|&idx| self.blocks[idx].name.clone())
+            .collect();
+        self.blocks[self.current].terminator = Terminator::Goto(successors);
+    }
+
+    fn push(&mut self, statement: Sp<Statement>) {
+        self.blocks[self.current].statements.push(statement);
+    }
+
+    // Ends the scope of the locals declared in `scope`, in reverse order of declaration.
+    fn end_scope(&mut self, scope: &[Name], span: Span) {
+        for variable in scope.iter().rev() {
+            self.push(Statement::StorageDead(variable.clone()).at(span));
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: Stmt) -> Result<(), &'static str> {
+        match stmt {
+            Stmt::Let { decl, init } => {
+                if self.variables.iter().any(|v| v.name == decl.name) {
+                    return Err("a new variable name, shadowing is not supported");
+                }
+                self.scopes
+                    .last_mut()
+                    .expect("Statements are lowered in a scope")
+                    .push(decl.name.clone());
+                self.variables.push(decl);
+                if let Some(mut init) = init {
+                    if let Statement::Assign(_, expr) = &mut *init {
+                        extend_temporaries(expr);
+                    }
+                    self.push(init);
+                }
+            }
+
+            Stmt::Statement(statement) => self.push(statement),
+
+            Stmt::If {
+                cond,
+                then_block,
+                else_block,
+            } => {
+                self.push(cond);
+                let then_idx = self.new_block();
+                let else_idx = else_block.as_ref().map(|_| self.new_block());
+                let join_idx = self.new_block();
+                self.goto(&[then_idx, else_idx.unwrap_or(join_idx)]);
+
+                self.current = then_idx;
+                self.lower_block(then_block)?;
+                self.goto(&[join_idx]);
+
+                if let (Some(else_idx), Some(else_block)) = (else_idx, else_block) {
+                    self.current = else_idx;
+                    self.lower_block(else_block)?;
+                    self.goto(&[join_idx]);
+                }
+                self.current = join_idx;
+            }
+
+            Stmt::While { cond, body } => {
+                let header_idx = self.new_block();
+                self.goto(&[header_idx]);
+                self.current = header_idx;
+                self.push(cond);
+
+                let body_idx = self.new_block();
+                let exit_idx = self.new_block();
+                self.goto(&[body_idx, exit_idx]);
+                self.lower_loop_body(body, body_idx, header_idx, exit_idx)?;
+            }
+
+            Stmt::Loop { body } => {
+                let body_idx = self.new_block();
+                let exit_idx = self.new_block();
+                self.goto(&[body_idx]);
+                self.lower_loop_body(body, body_idx, body_idx, exit_idx)?;
+            }
+
+            Stmt::Break(span) => {
+                let (scope_count, exit_idx) =
+                    *self.loops.last().ok_or("a `break` inside of a loop")?;
+                let exited_scopes: Vec<_> =
+                    self.scopes[scope_count..].iter().rev().cloned().collect();
+                for scope in exited_scopes {
+                    self.end_scope(&scope, span);
+                }
+                self.goto(&[exit_idx]);
+
+                // The statements after the `break` are unreachable
+                self.current = self.new_block();
+            }
+
+            Stmt::Block(block) => self.lower_block(block)?,
+        }
+        Ok(())
+    }
+
+    // Lowers the body of a loop, starting at `body_idx`, and continuing to `continue_idx` at the
+    // end of each iteration. The lowering then continues after the loop, at `exit_idx`.
+    fn lower_loop_body(
+        &mut self,
+        body: Block,
+        body_idx: usize,
+        continue_idx: usize,
+        exit_idx: usize,
+    ) -> Result<(), &'static str> {
+        self.loops.push((self.scopes.len(), exit_idx));
+        self.current = body_idx;
+        self.lower_block(body)?;
+        self.goto(&[continue_idx]);
+        self.loops.pop();
+
+        self.current = exit_idx;
+        Ok(())
+    }
+
+    fn lower_block(&mut self, block: Block) -> Result<(), &'static str> {
+        self.scopes.push(Vec::new());
+        for stmt in block.stmts {
+            self.lower_stmt(stmt)?;
+        }
+        let scope = self.scopes.pop().expect("The block's scope was pushed");
+        self.end_scope(&scope, block.end);
+        Ok(())
+    }
+}
+
+// Extends the lifetime of the temporaries borrowed in the initializer of a `let`, like rustc
+// does: the rvalues borrowed by the initializer itself, or by the fields of a struct literal or
+// the operand of a cast in an extended position, e.g. `let s: S = S { f: &temp };`. The
+// temporaries in call arguments are not extended.
+fn extend_temporaries(expr: &mut Expr) {
+    match expr {
+        Expr::BorrowRvalue {
+            rvalue, extended, ..
+        } => {
+            *extended = true;
+            extend_temporaries(rvalue);
+        }
+
+        Expr::Struct { fields, .. } => {
+            for (_, expr) in fields {
+                extend_temporaries(expr);
+            }
+        }
+
+        Expr::Cast { expr, .. } => extend_temporaries(expr),
+
+        _ => {}
+    }
+}
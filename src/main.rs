@@ -1,6 +1,18 @@
+use std::path::Path;
+
 fn main() -> eyre::Result<()> {
-    for arg in std::env::args().skip(1) {
-        polonius::test_harness(&arg)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        // Prints the input facts of a directory that an output tuple is derived from
+        [command, facts_path, tuple] if command == "slice" => {
+            print!("{}", polonius::slice_facts(Path::new(facts_path), tuple)?);
+        }
+
+        _ => {
+            for arg in &args {
+                polonius::test_harness(arg)?;
+            }
+        }
     }
     Ok(())
 }
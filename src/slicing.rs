@@ -0,0 +1,712 @@
+//! Slicing of the input facts: the subset of the facts an output tuple is derived from.
+//!
+//! On larger programs, most of the facts are unrelated to a given error. Slicing keeps the facts
+//! the derivation of an output tuple, e.g. `invalidated_origin_accessed('L_x, e)`, depends on:
+//! the CFG edges, subsets, invalidations and accesses it uses, and the clears of its origins at
+//! its nodes. They are written back in the fact file format, as a standalone reproducer.
+//!
+//! To know how each tuple is derived, the rules of `polonius.dl` are evaluated here, and the two
+//! must be kept in sync: the tests compare both evaluations. The evaluation proceeds in rounds, semi-naively, with the relations
+//! indexed by the atoms they're joined on. The derivation recorded for a tuple is the first one
+//! found, from tuples derived in earlier rounds: it's one of the shortest derivations of the
+//! tuple. Its inputs are then reduced to a minimal subset still deriving the tuple, where leaving
+//! out any of them would lose the tuple.
+//!
+//! Clears only appear negated in the rules: the derivation holds with all the clears, so the ones
+//! of the slice's origins at the slice's nodes can be kept, to avoid deriving unrelated tuples in
+//! the reproducer. The loans issued at the slice's nodes, and the markers of its cleanup nodes,
+//! are also kept, to describe them.
+
+#[cfg(test)]
+mod test;
+
+use crate::fact_parser::{parse_fact, read_facts, Fact};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+type Facts = HashMap<String, Vec<Vec<String>>>;
+
+/// A tuple of an input or output relation.
+type Tuple = (&'static str, Vec<String>);
+
+// Input relations
+const ACCESS_ORIGIN: &str = "access_origin";
+const DROP_ACCESS_ORIGIN: &str = "drop_access_origin";
+const INVALIDATE_ORIGIN: &str = "invalidate_origin";
+const CLEAR_ORIGIN: &str = "clear_origin";
+const INTRODUCE_SUBSET: &str = "introduce_subset";
+const KNOWN_SUBSET: &str = "known_subset";
+const MARK_AS_LOAN_ORIGIN: &str = "mark_as_loan_origin";
+const CFG_EDGE: &str = "cfg_edge";
+const LOAN_ISSUED_AT: &str = "loan_issued_at";
+const CLEANUP_NODE: &str = "cleanup_node";
+
+// Output relations
+const ORIGIN_USE_LIVE_ON_ENTRY: &str = "origin_use_live_on_entry";
+const ORIGIN_DROP_LIVE_ON_ENTRY: &str = "origin_drop_live_on_entry";
+const ORIGIN_LIVE_ON_ENTRY: &str = "origin_live_on_entry";
+const SUBSET_ON_EXIT: &str = "subset_on_exit";
+const SUBSET_ON_ENTRY: &str = "subset_on_entry";
+const ORIGIN_INVALIDATED: &str = "origin_invalidated";
+const INVALIDATED_ORIGIN_ACCESSED: &str = "invalidated_origin_accessed";
+const INVALIDATED_ORIGIN_ACCESSED_WHILE_UNWINDING: &str =
+    "invalidated_origin_accessed_while_unwinding";
+
+const OUTPUT_RELATIONS: &[&str] = &[
+    ORIGIN_USE_LIVE_ON_ENTRY,
+    ORIGIN_DROP_LIVE_ON_ENTRY,
+    ORIGIN_LIVE_ON_ENTRY,
+    SUBSET_ON_EXIT,
+    SUBSET_ON_ENTRY,
+    ORIGIN_INVALIDATED,
+    INVALIDATED_ORIGIN_ACCESSED,
+    INVALIDATED_ORIGIN_ACCESSED_WHILE_UNWINDING,
+];
+
+// The facts at each node, in the operational order described in the datalog rules.
+const NODE_RELATIONS: &[&str] = &[
+    CLEANUP_NODE,
+    ACCESS_ORIGIN,
+    DROP_ACCESS_ORIGIN,
+    INVALIDATE_ORIGIN,
+    CLEAR_ORIGIN,
+    INTRODUCE_SUBSET,
+    LOAN_ISSUED_AT,
+];
+
+const STATIC_ORIGIN: &str = "'static";
+
+/// Slices the input facts of the `facts_path` directory to the ones the output `tuple` is derived
+/// from, e.g. `invalidated_origin_accessed('L_x, e)`, and returns them in the fact file format.
+pub fn slice_facts(facts_path: &Path, tuple: &str) -> eyre::Result<String> {
+    let facts = read_facts(facts_path)?;
+    let target = parse_fact(tuple)?;
+    let slice = slice(&facts, &target)?;
+    Ok(format_facts(&slice, &target))
+}
+
+// Returns the input facts `target` is derived from, in the order of the input.
+fn slice(facts: &Facts, target: &Fact) -> eyre::Result<Facts> {
+    let relation = OUTPUT_RELATIONS
+        .iter()
+        .find(|relation| **relation == target.name)
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "unexpected relation `{}`, valid names are `{:?}`",
+                target.name,
+                OUTPUT_RELATIONS
+            )
+        })?;
+
+    let mut derivations = Derivations::new(facts);
+    derivations.evaluate();
+
+    let target_tuple: Tuple = (relation, target.arguments.clone());
+    if derivations.premises(&target_tuple).is_none() {
+        eyre::bail!("`{}` is not derived from these facts", format_fact(target));
+    }
+
+    // Collect the input tuples of the target's derivation
+    let mut inputs: HashSet<Tuple> = HashSet::new();
+    let mut visited: HashSet<Tuple> = HashSet::new();
+    let mut stack = vec![target_tuple.clone()];
+    while let Some(tuple) = stack.pop() {
+        if !visited.insert(tuple.clone()) {
+            continue;
+        }
+        match derivations.premises(&tuple) {
+            Some(premises) => stack.extend(premises.iter().cloned()),
+            None => {
+                inputs.insert(tuple);
+            }
+        }
+    }
+
+    // The target may still be derived from a part of these inputs, through other derivations:
+    // each input is left out in turn, when the target is derived without it.
+    let mut inputs: Vec<Tuple> = inputs.into_iter().collect();
+    inputs.sort();
+    let mut idx = 0;
+    while idx < inputs.len() {
+        let input = inputs.remove(idx);
+        if input.0 == CLEANUP_NODE || !is_derived(facts, &inputs, &target_tuple) {
+            inputs.insert(idx, input);
+            idx += 1;
+        }
+    }
+    let mut inputs: HashSet<Tuple> = inputs.into_iter().collect();
+
+    // Keep the clears of the slice's origins, and its loans, at the slice's nodes
+    let mut origins = HashSet::new();
+    let mut nodes = HashSet::new();
+    for (relation, row) in &inputs {
+        match *relation {
+            CFG_EDGE => nodes.extend(row.iter().cloned()),
+            KNOWN_SUBSET | MARK_AS_LOAN_ORIGIN => origins.extend(row.iter().cloned()),
+            _ => {
+                let (node, row_origins) = row.split_last().expect("Node facts have a node");
+                nodes.insert(node.clone());
+                origins.extend(row_origins.iter().cloned());
+            }
+        }
+    }
+    for relation in &[CLEAR_ORIGIN, LOAN_ISSUED_AT] {
+        for row in &facts[*relation] {
+            if origins.contains(&row[0]) && nodes.contains(&row[row.len() - 1]) {
+                inputs.insert((relation, row.clone()));
+            }
+        }
+    }
+    for row in &facts[CLEANUP_NODE] {
+        if nodes.contains(&row[0]) {
+            inputs.insert((CLEANUP_NODE, row.clone()));
+        }
+    }
+
+    let mut slice: Facts = HashMap::new();
+    for (relation, rows) in facts {
+        let rows = rows
+            .iter()
+            .filter(|row| match relation.as_str() {
+                "node_text" => nodes.contains(&row[1]),
+                _ => inputs.contains(&(relation_name(relation), row.to_vec())),
+            })
+            .cloned()
+            .collect();
+        slice.insert(relation.clone(), rows);
+    }
+    Ok(slice)
+}
+
+// Returns true if `target` is derived from the `inputs`, with all the clears and cleanup nodes of
+// the `facts`: the slice keeps less of them, which can only derive more tuples.
+fn is_derived(facts: &Facts, inputs: &[Tuple], target: &Tuple) -> bool {
+    let mut subset: Facts = facts
+        .keys()
+        .map(|relation| (relation.clone(), Vec::new()))
+        .collect();
+    for relation in &[CLEAR_ORIGIN, CLEANUP_NODE] {
+        subset.insert(relation.to_string(), facts[*relation].clone());
+    }
+    for (relation, row) in inputs {
+        subset
+            .get_mut(*relation)
+            .expect("The inputs are tuples of the facts' relations")
+            .push(row.clone());
+    }
+
+    let mut derivations = Derivations::new(&subset);
+    derivations.evaluate();
+    derivations.premises(target).is_some()
+}
+
+// Returns the static name of an input relation.
+fn relation_name(relation: &str) -> &'static str {
+    NODE_RELATIONS
+        .iter()
+        .chain(&[KNOWN_SUBSET, MARK_AS_LOAN_ORIGIN, CFG_EDGE])
+        .find(|candidate| **candidate == relation)
+        .copied()
+        .unwrap_or("")
+}
+
+// Formats the facts as a fact file: the global facts, then the nodes in the order of the input,
+// with their facts and successors.
+fn format_facts(facts: &Facts, target: &Fact) -> String {
+    let mut output = String::new();
+    writeln!(
+        output,
+        "// The facts `{}` is derived from",
+        format_fact(target)
+    )
+    .unwrap();
+    if !facts[LOAN_ISSUED_AT].is_empty() {
+        writeln!(output, "//").unwrap();
+        writeln!(output, "// Loans:").unwrap();
+    }
+    for row in &facts[LOAN_ISSUED_AT] {
+        let (origin, place, mode, node) = (&row[0], &row[1], &row[2], &row[3]);
+        writeln!(
+            output,
+            "// {}: {} borrow of `{}` at {}",
+            origin, mode, place, node
+        )
+        .unwrap();
+    }
+    writeln!(output).unwrap();
+
+    for relation in &[KNOWN_SUBSET, MARK_AS_LOAN_ORIGIN] {
+        for row in &facts[*relation] {
+            writeln!(output, "{}({})", relation, row.join(", ")).unwrap();
+        }
+    }
+    if !facts[KNOWN_SUBSET].is_empty() || !facts[MARK_AS_LOAN_ORIGIN].is_empty() {
+        writeln!(output).unwrap();
+    }
+
+    // Index the facts by node, in the operational order of the relations
+    let mut texts: HashMap<&str, &str> = HashMap::new();
+    for row in &facts["node_text"] {
+        texts.entry(&row[1]).or_insert(&row[0]);
+    }
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    for row in &facts[CFG_EDGE] {
+        successors.entry(&row[0]).or_default().push(&row[1]);
+    }
+    let mut facts_per_node: HashMap<&str, Vec<(&str, &[String])>> = HashMap::new();
+    for relation in NODE_RELATIONS {
+        for row in &facts[*relation] {
+            let (node, atoms) = row.split_last().expect("Node facts have a node");
+            facts_per_node
+                .entry(node)
+                .or_default()
+                .push((relation, atoms));
+        }
+    }
+
+    // The nodes with a `node_text` come first, in the order of the input
+    let mut nodes: Vec<&str> = Vec::new();
+    let mut seen_nodes = HashSet::new();
+    let node_texts = facts["node_text"].iter().map(|row| &row[1]);
+    let edge_nodes = facts[CFG_EDGE].iter().flatten();
+    let fact_nodes = NODE_RELATIONS
+        .iter()
+        .flat_map(|relation| facts[*relation].iter().map(|row| &row[row.len() - 1]));
+    for node in node_texts.chain(edge_nodes).chain(fact_nodes) {
+        if seen_nodes.insert(node) {
+            nodes.push(node);
+        }
+    }
+
+    for (node_idx, node) in nodes.into_iter().enumerate() {
+        if node_idx != 0 {
+            writeln!(output).unwrap();
+        }
+
+        let text = texts.get(node).copied().unwrap_or("(pass)");
+        writeln!(output, "{}: {:?} {{", node, text).unwrap();
+
+        for (relation, atoms) in facts_per_node.get(node).into_iter().flatten() {
+            if *relation == LOAN_ISSUED_AT {
+                let (origin, place, mode) = (&atoms[0], &atoms[1], &atoms[2]);
+                writeln!(output, "\t{}({}, {:?}, {})", relation, origin, place, mode).unwrap();
+            } else {
+                writeln!(output, "\t{}({})", relation, atoms.join(", ")).unwrap();
+            }
+        }
+
+        write!(output, "\tgoto").unwrap();
+        for successor in successors.get(node).into_iter().flatten() {
+            write!(output, " {}", successor).unwrap();
+        }
+        writeln!(output, "\n}}").unwrap();
+    }
+
+    output
+}
+
+fn format_fact(fact: &Fact) -> String {
+    format!("{}({})", fact.name, fact.arguments.join(", "))
+}
+
+fn tuple(relation: &'static str, row: &[&str]) -> Tuple {
+    (relation, row.iter().map(|s| s.to_string()).collect())
+}
+
+/// The evaluation of the datalog rules, recording the derivation of each output tuple.
+struct Derivations<'a> {
+    facts: &'a Facts,
+
+    /// The input facts, indexed by the atoms the rules join or filter them on.
+    clears: HashSet<(&'a str, &'a str)>,
+    invalidations: HashSet<(&'a str, &'a str)>,
+    accesses: HashSet<(&'static str, &'a str, &'a str)>,
+    loan_origins: HashSet<&'a str>,
+    cleanup_nodes: HashSet<&'a str>,
+
+    /// The CFG edges from and to each node.
+    successors: HashMap<&'a str, Vec<&'a Vec<String>>>,
+    predecessors: HashMap<&'a str, Vec<&'a Vec<String>>>,
+
+    /// The nodes of the CFG, with an edge they're part of.
+    nodes: Vec<(&'a str, &'a [String])>,
+
+    /// The output tuples of each relation, with the tuples they're derived from.
+    derived: HashMap<&'static str, HashMap<Vec<String>, Vec<Tuple>>>,
+
+    /// The `subset_on_exit` tuples, indexed by their first origin and node, and by their second
+    /// origin and node.
+    subsets_from: HashMap<(String, String), Vec<Vec<String>>>,
+    subsets_to: HashMap<(String, String), Vec<Vec<String>>>,
+}
+
+impl<'a> Derivations<'a> {
+    fn new(facts: &'a Facts) -> Self {
+        let pairs = |relation: &str| -> HashSet<(&'a str, &'a str)> {
+            facts[relation]
+                .iter()
+                .map(|row| (row[0].as_str(), row[1].as_str()))
+                .collect()
+        };
+        let accesses = [ACCESS_ORIGIN, DROP_ACCESS_ORIGIN]
+            .iter()
+            .flat_map(|relation| {
+                facts[*relation]
+                    .iter()
+                    .map(move |row| (*relation, row[0].as_str(), row[1].as_str()))
+            })
+            .collect();
+        let loan_origins = facts[MARK_AS_LOAN_ORIGIN]
+            .iter()
+            .map(|row| row[0].as_str())
+            .collect();
+        let cleanup_nodes = facts[CLEANUP_NODE]
+            .iter()
+            .map(|row| row[0].as_str())
+            .collect();
+
+        let mut successors: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        let mut predecessors: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        let mut nodes: Vec<(&str, &[String])> = Vec::new();
+        let mut seen_nodes = HashSet::new();
+        for edge in &facts[CFG_EDGE] {
+            successors.entry(&edge[0]).or_default().push(edge);
+            predecessors.entry(&edge[1]).or_default().push(edge);
+            for node in edge {
+                if seen_nodes.insert(node) {
+                    nodes.push((node, edge));
+                }
+            }
+        }
+
+        Self {
+            facts,
+            clears: pairs(CLEAR_ORIGIN),
+            invalidations: pairs(INVALIDATE_ORIGIN),
+            accesses,
+            loan_origins,
+            cleanup_nodes,
+            successors,
+            predecessors,
+            nodes,
+            derived: HashMap::new(),
+            subsets_from: HashMap::new(),
+            subsets_to: HashMap::new(),
+        }
+    }
+
+    // Returns the tuples an output tuple is derived from, or `None` for input tuples and tuples
+    // which are not derived.
+    fn premises(&self, (relation, row): &Tuple) -> Option<&Vec<Tuple>> {
+        self.derived.get(relation)?.get(row)
+    }
+
+    fn contains(&self, relation: &'static str, row: &[&str]) -> bool {
+        let row: Vec<String> = row.iter().map(|s| s.to_string()).collect();
+        self.derived
+            .get(relation)
+            .is_some_and(|rows| rows.contains_key(&row))
+    }
+
+    #[cfg(test)]
+    fn rows(&self, relation: &'static str) -> impl Iterator<Item = &Vec<String>> {
+        self.derived
+            .get(relation)
+            .into_iter()
+            .flat_map(|rows| rows.keys())
+    }
+
+    fn is_cleared(&self, origin: &str, node: &str) -> bool {
+        self.clears.contains(&(origin, node))
+    }
+
+    fn successors(&self, node: &str) -> impl Iterator<Item = &'a Vec<String>> + '_ {
+        self.successors.get(node).into_iter().flatten().copied()
+    }
+
+    fn predecessors(&self, node: &str) -> impl Iterator<Item = &'a Vec<String>> + '_ {
+        self.predecessors.get(node).into_iter().flatten().copied()
+    }
+
+    fn subsets<'b>(
+        index: &'b HashMap<(String, String), Vec<Vec<String>>>,
+        origin: &str,
+        node: &str,
+    ) -> impl Iterator<Item = &'b Vec<String>> {
+        index
+            .get(&(origin.to_string(), node.to_string()))
+            .into_iter()
+            .flatten()
+    }
+
+    // Derives the tuples until a fixpoint is reached, semi-naively: the tuples derived in a round
+    // are only added at its end, and the next round only evaluates the rules with one of them as
+    // a premise. The derivations recorded in a round thus only use the tuples of earlier rounds.
+    fn evaluate(&mut self) {
+        let mut derived = self.derive_from_inputs();
+        loop {
+            let delta = self.insert(derived);
+            if delta.is_empty() {
+                break;
+            }
+            derived = self.derive_from_delta(&delta);
+        }
+    }
+
+    // Adds the derived tuples which are new, with their premises, and returns them.
+    fn insert(&mut self, derived: Vec<(Tuple, Vec<Tuple>)>) -> Vec<Tuple> {
+        let mut delta = Vec::new();
+        for ((relation, row), premises) in derived {
+            let rows = self.derived.entry(relation).or_default();
+            if let Entry::Vacant(entry) = rows.entry(row.clone()) {
+                entry.insert(premises);
+                if relation == SUBSET_ON_EXIT {
+                    let (o1, o2, n) = (&row[0], &row[1], &row[2]);
+                    self.subsets_from
+                        .entry((o1.clone(), n.clone()))
+                        .or_default()
+                        .push(row.clone());
+                    self.subsets_to
+                        .entry((o2.clone(), n.clone()))
+                        .or_default()
+                        .push(row.clone());
+                }
+                delta.push((relation, row));
+            }
+        }
+        delta
+    }
+
+    // Evaluates the rules whose premises are all input facts.
+    fn derive_from_inputs(&self) -> Vec<(Tuple, Vec<Tuple>)> {
+        let mut derived = Vec::new();
+
+        // Use-liveness and drop-liveness of the accessed origins
+        for (access, live) in &[
+            (ACCESS_ORIGIN, ORIGIN_USE_LIVE_ON_ENTRY),
+            (DROP_ACCESS_ORIGIN, ORIGIN_DROP_LIVE_ON_ENTRY),
+        ] {
+            for row in &self.facts[*access] {
+                derived.push(((*live, row.clone()), vec![(*access, row.clone())]));
+            }
+        }
+
+        // The `'static` origin is live everywhere
+        for (node, edge) in &self.nodes {
+            derived.push((
+                tuple(ORIGIN_LIVE_ON_ENTRY, &[STATIC_ORIGIN, node]),
+                vec![(CFG_EDGE, edge.to_vec())],
+            ));
+        }
+
+        // Subsets on exit: introduced, and known
+        for row in &self.facts[INTRODUCE_SUBSET] {
+            derived.push((
+                (SUBSET_ON_EXIT, row.clone()),
+                vec![(INTRODUCE_SUBSET, row.clone())],
+            ));
+        }
+        for row in &self.facts[KNOWN_SUBSET] {
+            for (node, edge) in &self.nodes {
+                derived.push((
+                    tuple(SUBSET_ON_EXIT, &[&row[0], &row[1], node]),
+                    vec![(KNOWN_SUBSET, row.clone()), (CFG_EDGE, edge.to_vec())],
+                ));
+            }
+        }
+
+        // Invalidations introduced by the predecessor
+        for row in &self.facts[INVALIDATE_ORIGIN] {
+            self.derive_invalidation(INVALIDATE_ORIGIN, row, &mut derived);
+        }
+
+        derived
+    }
+
+    // Evaluates the rules with one of the tuples derived in the last round as a premise, joined
+    // with the tuples derived until then.
+    fn derive_from_delta(&self, delta: &[Tuple]) -> Vec<(Tuple, Vec<Tuple>)> {
+        let mut derived = Vec::new();
+        for (relation, row) in delta {
+            match *relation {
+                // Liveness: propagated to the predecessors where the origin is not cleared
+                ORIGIN_USE_LIVE_ON_ENTRY | ORIGIN_DROP_LIVE_ON_ENTRY => {
+                    let (origin, n2) = (&row[0], &row[1]);
+                    for edge in self.predecessors(n2) {
+                        let n1 = &edge[0];
+                        if !self.is_cleared(origin, n1) {
+                            derived.push((
+                                tuple(relation, &[origin, n1]),
+                                vec![(CFG_EDGE, edge.clone()), (relation, row.clone())],
+                            ));
+                        }
+                    }
+                    derived.push((
+                        (ORIGIN_LIVE_ON_ENTRY, row.clone()),
+                        vec![(relation, row.clone())],
+                    ));
+                }
+
+                // `'static` outlives the live origins at the predecessors, and the subsets
+                // between live origins are carried over from the predecessors
+                ORIGIN_LIVE_ON_ENTRY => {
+                    let (origin, n2) = (&row[0], &row[1]);
+                    for edge in self.predecessors(n2) {
+                        let n1 = &edge[0];
+                        if origin != STATIC_ORIGIN {
+                            derived.push((
+                                tuple(SUBSET_ON_EXIT, &[STATIC_ORIGIN, origin, n1]),
+                                vec![(CFG_EDGE, edge.clone()), (relation, row.clone())],
+                            ));
+                        }
+
+                        let subsets = Self::subsets(&self.subsets_from, origin, n1)
+                            .chain(Self::subsets(&self.subsets_to, origin, n1));
+                        for subset in subsets {
+                            self.derive_subset_on_entry(edge, subset, &mut derived);
+                        }
+                    }
+                }
+
+                // Subsets on exit: the transitive closure, and the subsets carried over to the
+                // successors
+                SUBSET_ON_EXIT => {
+                    let (o1, o2, n) = (&row[0], &row[1], &row[2]);
+                    for row2 in Self::subsets(&self.subsets_from, o2, n) {
+                        derived.push((
+                            tuple(SUBSET_ON_EXIT, &[o1, &row2[1], n]),
+                            vec![
+                                (SUBSET_ON_EXIT, row.clone()),
+                                (SUBSET_ON_EXIT, row2.clone()),
+                            ],
+                        ));
+                    }
+                    for row1 in Self::subsets(&self.subsets_to, o1, n) {
+                        derived.push((
+                            tuple(SUBSET_ON_EXIT, &[&row1[0], o2, n]),
+                            vec![
+                                (SUBSET_ON_EXIT, row1.clone()),
+                                (SUBSET_ON_EXIT, row.clone()),
+                            ],
+                        ));
+                    }
+
+                    for edge in self.successors(n) {
+                        self.derive_subset_on_entry(edge, row, &mut derived);
+                    }
+                }
+
+                // Subsets on entry: filtered into the subsets on exit, and propagating the
+                // invalidations introduced at the node
+                SUBSET_ON_ENTRY => {
+                    let (o1, o2, n1) = (&row[0], &row[1], &row[2]);
+                    if !self.is_cleared(o1, n1) && !self.is_cleared(o2, n1) {
+                        derived.push((
+                            (SUBSET_ON_EXIT, row.clone()),
+                            vec![(SUBSET_ON_ENTRY, row.clone())],
+                        ));
+                    }
+
+                    if self.invalidations.contains(&(o1.as_str(), n1.as_str()))
+                        && !self.is_cleared(o2, n1)
+                    {
+                        for edge in self.successors(n1) {
+                            derived.push((
+                                tuple(ORIGIN_INVALIDATED, &[o2, &edge[1]]),
+                                vec![
+                                    (CFG_EDGE, edge.clone()),
+                                    (SUBSET_ON_ENTRY, row.clone()),
+                                    tuple(INVALIDATE_ORIGIN, &[o1, n1]),
+                                ],
+                            ));
+                        }
+                    }
+                }
+
+                // Invalidations: propagated to the successors, and errors when accessed
+                ORIGIN_INVALIDATED => {
+                    self.derive_invalidation(ORIGIN_INVALIDATED, row, &mut derived);
+
+                    // The errors on cleanup paths are kept separate from the other ones
+                    let (origin, node) = (&row[0], &row[1]);
+                    let is_cleanup = self.cleanup_nodes.contains(node.as_str());
+                    for access in &[ACCESS_ORIGIN, DROP_ACCESS_ORIGIN] {
+                        if !self
+                            .accesses
+                            .contains(&(*access, origin.as_str(), node.as_str()))
+                        {
+                            continue;
+                        }
+                        let mut premises = vec![(*access, row.clone()), (relation, row.clone())];
+                        if is_cleanup {
+                            premises.push(tuple(CLEANUP_NODE, &[node]));
+                            derived.push((
+                                (INVALIDATED_ORIGIN_ACCESSED_WHILE_UNWINDING, row.clone()),
+                                premises,
+                            ));
+                        } else {
+                            derived.push(((INVALIDATED_ORIGIN_ACCESSED, row.clone()), premises));
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+        derived
+    }
+
+    // Derives the invalidation of the origin at the successors of the node, unless it's cleared
+    // there.
+    fn derive_invalidation(
+        &self,
+        relation: &'static str,
+        row: &[String],
+        derived: &mut Vec<(Tuple, Vec<Tuple>)>,
+    ) {
+        let (origin, n1) = (&row[0], &row[1]);
+        if self.is_cleared(origin, n1) {
+            return;
+        }
+        for edge in self.successors(n1) {
+            derived.push((
+                tuple(ORIGIN_INVALIDATED, &[origin, &edge[1]]),
+                vec![(CFG_EDGE, edge.clone()), (relation, row.to_vec())],
+            ));
+        }
+    }
+
+    // Derives the subset on entry of the edge's target, from the subset on exit of its source,
+    // if both origins are live or loan origins there.
+    fn derive_subset_on_entry(
+        &self,
+        edge: &[String],
+        subset: &[String],
+        derived: &mut Vec<(Tuple, Vec<Tuple>)>,
+    ) {
+        let live_or_loan = |origin: &str, node: &str| -> Option<Tuple> {
+            if self.contains(ORIGIN_LIVE_ON_ENTRY, &[origin, node]) {
+                return Some(tuple(ORIGIN_LIVE_ON_ENTRY, &[origin, node]));
+            }
+            if self.loan_origins.contains(origin) {
+                return Some(tuple(MARK_AS_LOAN_ORIGIN, &[origin]));
+            }
+            None
+        };
+
+        let (o1, o2, n2) = (&subset[0], &subset[1], &edge[1]);
+        if let (Some(premise1), Some(premise2)) = (live_or_loan(o1, n2), live_or_loan(o2, n2)) {
+            derived.push((
+                tuple(SUBSET_ON_ENTRY, &[o1, o2, n2]),
+                vec![
+                    (CFG_EDGE, edge.to_vec()),
+                    premise1,
+                    premise2,
+                    (SUBSET_ON_EXIT, subset.to_vec()),
+                ],
+            ));
+        }
+    }
+}
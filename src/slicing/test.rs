@@ -0,0 +1,659 @@
+use super::*;
+use crate::fact_parser::{collect_facts, parse_facts};
+
+// An error on the cleanup path of a call: the tests have no cleanup nodes.
+const UNWINDING_PROGRAM: &str = "
+    mark_as_loan_origin('L_x)
+
+    a: \"p = &'L_x x\" {
+        clear_origin('p)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'p)
+        loan_issued_at('L_x, \"x\", shared)
+        goto b
+    }
+
+    b: \"f()\" {
+        goto c d
+    }
+
+    c: \"return\" {
+        goto
+    }
+
+    d: \"x = 2\" {
+        cleanup_node()
+        invalidate_origin('L_x)
+        goto e
+    }
+
+    e: \"use(copy p)\" {
+        cleanup_node()
+        access_origin('p)
+        goto
+    }
+";
+
+fn expect_facts(program: &str) -> Facts {
+    let functions = parse_facts(program).expect("Unexpected parsing error");
+    assert_eq!(functions.len(), 1, "Expected a single function");
+    collect_facts(&functions[0].1).expect("Unexpected facts")
+}
+
+fn expect_slice(program: &str, tuple: &str) -> String {
+    let facts = expect_facts(program);
+    let target = parse_fact(tuple).expect("Unexpected parsing error");
+    let slice = slice(&facts, &target).expect("Unexpected slicing error");
+    let output = format_facts(&slice, &target);
+    expect_reproducer(&output, &target);
+    output
+}
+
+// Checks that the formatted slice is a fact file the target is still derived from.
+fn expect_reproducer(output: &str, target: &Fact) {
+    let facts = expect_facts(output);
+    let mut derivations = Derivations::new(&facts);
+    derivations.evaluate();
+    assert!(
+        derivations
+            .premises(&(output_relation(target), target.arguments.clone()))
+            .is_some(),
+        "`{}` is not derived from its slice:\n{}",
+        format_fact(target),
+        output
+    );
+}
+
+fn output_relation(target: &Fact) -> &'static str {
+    OUTPUT_RELATIONS
+        .iter()
+        .find(|relation| **relation == target.name)
+        .expect("The target is an output tuple")
+}
+
+// Checks that the target is no longer derived when leaving out any of the slice's inputs, other
+// than the clears, loans and cleanup nodes kept to avoid deriving unrelated tuples.
+fn expect_minimal(facts: &Facts, slice: &Facts, target: &Fact) {
+    let mut inputs = Vec::new();
+    for (relation, rows) in slice {
+        let relation = relation_name(relation);
+        if ![CLEAR_ORIGIN, LOAN_ISSUED_AT, CLEANUP_NODE, ""].contains(&relation) {
+            inputs.extend(rows.iter().map(|row| (relation, row.clone())));
+        }
+    }
+
+    let target_tuple = (output_relation(target), target.arguments.clone());
+    for idx in 0..inputs.len() {
+        let mut subset = inputs.clone();
+        let (relation, row) = subset.remove(idx);
+        assert!(
+            !is_derived(facts, &subset, &target_tuple),
+            "`{}` is still derived without `{}({})`",
+            format_fact(target),
+            relation,
+            row.join(", ")
+        );
+    }
+}
+
+#[test]
+fn evaluation_matches_the_expected_results() {
+    // The rules evaluated here compute the same errors as the datalog rules, in each test
+    for entry in std::fs::read_dir("tests").unwrap() {
+        let path = entry.unwrap().path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let program = std::fs::read_to_string(path.join("program.txt")).unwrap();
+        let expected =
+            std::fs::read_to_string(path.join("invalidated_origin_accessed.csv")).unwrap();
+
+        let mut errors = Vec::new();
+        for (name, program) in parse_facts(&program).unwrap() {
+            let facts = collect_facts(&program).unwrap();
+            let mut derivations = Derivations::new(&facts);
+            derivations.evaluate();
+            for row in derivations.rows(INVALIDATED_ORIGIN_ACCESSED) {
+                let row = name.iter().chain(row).cloned().collect::<Vec<_>>();
+                errors.push(row.join("\t"));
+            }
+        }
+        errors.sort();
+
+        let mut expected: Vec<_> = expected.lines().collect();
+        expected.sort();
+        assert_eq!(errors, expected, "Unexpected errors in {}", path.display());
+    }
+}
+
+#[test]
+fn slices_reproduce_the_errors() {
+    // Each error of the tests is derived again from the fact file of its slice
+    for entry in std::fs::read_dir("tests").unwrap() {
+        let path = entry.unwrap().path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let program = std::fs::read_to_string(path.join("program.txt")).unwrap();
+        for (_, program) in parse_facts(&program).unwrap() {
+            let facts = collect_facts(&program).unwrap();
+            let mut derivations = Derivations::new(&facts);
+            derivations.evaluate();
+
+            for relation in &[
+                INVALIDATED_ORIGIN_ACCESSED,
+                INVALIDATED_ORIGIN_ACCESSED_WHILE_UNWINDING,
+            ] {
+                for row in derivations.rows(relation) {
+                    let target = Fact {
+                        name: relation.to_string(),
+                        arguments: row.clone(),
+                    };
+                    let slice = slice(&facts, &target).unwrap();
+                    expect_reproducer(&format_facts(&slice, &target), &target);
+                    expect_minimal(&facts, &slice, &target);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn slice_of_a_partial_overwrite() {
+    // The borrows of `x` are unrelated to the error
+    let program = include_str!("../../tests/partial-overwrite-err/program.txt");
+    insta::assert_display_snapshot!(expect_slice(program, "invalidated_origin_accessed('p2, f)"), @r###"
+    // The facts `invalidated_origin_accessed('p2, f)` is derived from
+
+    mark_as_loan_origin('L_y)
+
+    c: "p = Pair { first: &'L_x1 x, second: &'L_y y }" {
+    	clear_origin('p2)
+    	clear_origin('L_y)
+    	introduce_subset('L_y, 'p2)
+    	goto d
+    }
+
+    d: "p.first = &'L_x2 x" {
+    	goto e
+    }
+
+    e: "y = 24" {
+    	invalidate_origin('L_y)
+    	goto f
+    }
+
+    f: "r = copy p.second" {
+    	access_origin('p2)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn slice_of_a_loop() {
+    // The error is only reached on the loop's back edge
+    let program = include_str!("../../tests/self-invalidation-loop/program.txt");
+    insta::assert_display_snapshot!(expect_slice(program, "invalidated_origin_accessed('v, d)"), @r###"
+    // The facts `invalidated_origin_accessed('v, d)` is derived from
+
+    mark_as_loan_origin('L_x)
+
+    c: "p = &'L_x mut x" {
+    	invalidate_origin('L_x)
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	goto d
+    }
+
+    d: "v_tmp = &'L_v mut v" {
+    	access_origin('v)
+    	clear_origin('tmp1)
+    	introduce_subset('tmp1, 'v)
+    	goto e
+    }
+
+    e: "Vec::push(v_tmp, p)" {
+    	access_origin('tmp1)
+    	access_origin('p)
+    	introduce_subset('p, 'tmp1)
+    	goto c
+    }
+    "###);
+}
+
+#[test]
+fn slice_of_an_intermediate_tuple() {
+    let program = include_str!("../../tests/example-a/program.txt");
+    insta::assert_display_snapshot!(expect_slice(program, "subset_on_entry('0, 'y, c)"), @r###"
+    // The facts `subset_on_entry('0, 'y, c)` is derived from
+
+    mark_as_loan_origin('0)
+
+    b: "y = &'0 x" {
+    	clear_origin('0)
+    	introduce_subset('0, 'y)
+    	goto c
+    }
+
+    c: "x = 4" {
+    	goto d
+    }
+
+    d: "drop(y)" {
+    	access_origin('y)
+    	goto
+    }
+    "###);
+}
+
+#[test]
+fn slice_of_an_error_while_unwinding() {
+    // The call's normal successor is unrelated to the error on its cleanup path
+    let tuple = "invalidated_origin_accessed_while_unwinding('p, e)";
+    insta::assert_display_snapshot!(expect_slice(UNWINDING_PROGRAM, tuple), @r###"
+    // The facts `invalidated_origin_accessed_while_unwinding('p, e)` is derived from
+    //
+    // Loans:
+    // 'L_x: shared borrow of `x` at a
+
+    mark_as_loan_origin('L_x)
+
+    a: "p = &'L_x x" {
+    	clear_origin('p)
+    	clear_origin('L_x)
+    	introduce_subset('L_x, 'p)
+    	loan_issued_at('L_x, "x", shared)
+    	goto b
+    }
+
+    b: "f()" {
+    	goto d
+    }
+
+    d: "x = 2" {
+    	cleanup_node()
+    	invalidate_origin('L_x)
+    	goto e
+    }
+
+    e: "use(copy p)" {
+    	cleanup_node()
+    	access_origin('p)
+    	goto
+    }
+    "###);
+
+    // It's not an error on the regular paths
+    let facts = expect_facts(UNWINDING_PROGRAM);
+    let target = parse_fact("invalidated_origin_accessed('p, e)").unwrap();
+    assert!(slice(&facts, &target).is_err());
+}
+
+#[test]
+fn slicing_errors() {
+    let facts = expect_facts(include_str!("../../tests/example-a/program.txt"));
+
+    let target = parse_fact("invalidated_origin_accessed('y, a)").unwrap();
+    let error = slice(&facts, &target).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`invalidated_origin_accessed('y, a)` is not derived from these facts"
+    );
+
+    let target = parse_fact("access_origin('y, d)").unwrap();
+    let error = slice(&facts, &target).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("unexpected relation `access_origin`"));
+}
+
+#[test]
+fn evaluation_matches_the_datalog_rules() {
+    // The rules evaluated here are the rules of `polonius.dl`: the two evaluations derive the same
+    // tuples in each output relation, for the tests and for a cleanup path.
+    let rules = datalog::Rules::parse(include_str!("../polonius.dl"));
+    let mut outputs = rules.outputs.clone();
+    outputs.sort();
+    let mut expected: Vec<_> = OUTPUT_RELATIONS.iter().map(|r| r.to_string()).collect();
+    expected.sort();
+    assert_eq!(
+        outputs, expected,
+        "The output relations of `polonius.dl` changed"
+    );
+
+    let mut programs = vec![("cleanup path".to_string(), UNWINDING_PROGRAM.to_string())];
+    for entry in std::fs::read_dir("tests").unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            let program = std::fs::read_to_string(path.join("program.txt")).unwrap();
+            programs.push((path.display().to_string(), program));
+        }
+    }
+
+    for (path, program) in programs {
+        for (name, program) in parse_facts(&program).unwrap() {
+            let facts = collect_facts(&program).unwrap();
+            let mut derivations = Derivations::new(&facts);
+            derivations.evaluate();
+            let expected = rules.evaluate(&facts);
+
+            for relation in OUTPUT_RELATIONS {
+                let mut rows: Vec<_> = derivations.rows(relation).cloned().collect();
+                rows.sort();
+                let mut expected: Vec<_> = expected[*relation].iter().cloned().collect();
+                expected.sort();
+                assert_eq!(
+                    rows, expected,
+                    "Unexpected `{}` tuples in {} ({:?})",
+                    relation, path, name
+                );
+            }
+        }
+    }
+}
+
+// A naive evaluation of the datalog rules, to check the rules evaluated in `slicing.rs` against
+// `polonius.dl`.
+mod datalog {
+    use std::collections::{HashMap, HashSet};
+
+    type Relations = HashMap<String, HashSet<Vec<String>>>;
+
+    #[derive(Debug, Clone)]
+    enum Term {
+        Variable(String),
+        Constant(String),
+        Wildcard,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Literal {
+        Positive(String, Vec<Term>),
+        Negative(String, Vec<Term>),
+        NotEqual(Term, Term),
+    }
+
+    struct Rule {
+        head: (String, Vec<Term>),
+        // The disjunctions of the body are expanded: the head holds when any of the conjunctions
+        // holds.
+        bodies: Vec<Vec<Literal>>,
+    }
+
+    pub(super) struct Rules {
+        rules: Vec<Rule>,
+        pub(super) outputs: Vec<String>,
+    }
+
+    impl Rules {
+        pub(super) fn parse(source: &str) -> Self {
+            let mut outputs = Vec::new();
+            let mut inputs = Vec::new();
+            let mut text = String::new();
+            for line in source.lines() {
+                let line = line.split("//").next().unwrap().trim();
+                match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [".output", relation] => outputs.push(relation.to_string()),
+                    [".input", relation] => inputs.push(relation.to_string()),
+                    _ if line.starts_with('.') => {}
+                    _ => {
+                        text.push_str(line);
+                        text.push(' ');
+                    }
+                }
+            }
+            // The informational relations output as they're input are not derived
+            outputs.retain(|relation| !inputs.contains(relation));
+
+            let mut parser = Parser {
+                tokens: tokenize(&text),
+                idx: 0,
+            };
+            let mut rules = Vec::new();
+            while parser.idx < parser.tokens.len() {
+                let head = parser.atom();
+                parser.expect(":-");
+                let bodies = parser.disjunction();
+                parser.expect(".");
+                rules.push(Rule { head, bodies });
+            }
+            Self { rules, outputs }
+        }
+
+        // Evaluates the rules on the facts, until no new tuples are derived.
+        pub(super) fn evaluate(&self, facts: &super::Facts) -> Relations {
+            let mut relations: Relations = facts
+                .iter()
+                .map(|(relation, rows)| (relation.clone(), rows.iter().cloned().collect()))
+                .collect();
+            for rule in &self.rules {
+                relations.entry(rule.head.0.clone()).or_default();
+            }
+
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for rule in &self.rules {
+                    for body in &rule.bodies {
+                        for row in evaluate_body(&rule.head.1, body, &relations) {
+                            changed |= relations.get_mut(&rule.head.0).unwrap().insert(row);
+                        }
+                    }
+                }
+            }
+            relations
+        }
+    }
+
+    type Bindings = HashMap<String, String>;
+
+    // Returns the head tuples of the bindings satisfying the body: the positive literals are
+    // joined in order, then the bindings are filtered by the negations and inequalities.
+    fn evaluate_body(head: &[Term], body: &[Literal], relations: &Relations) -> Vec<Vec<String>> {
+        let mut bindings: Vec<Bindings> = vec![HashMap::new()];
+        for literal in body {
+            let (relation, terms) = match literal {
+                Literal::Positive(relation, terms) => (relation, terms),
+                _ => continue,
+            };
+
+            // Index the relation on the positions bound before the literal
+            let bound: Vec<usize> = (0..terms.len())
+                .filter(|idx| match &terms[*idx] {
+                    Term::Variable(variable) => bindings
+                        .first()
+                        .map_or(false, |binding| binding.contains_key(variable)),
+                    Term::Constant(_) => true,
+                    Term::Wildcard => false,
+                })
+                .collect();
+            let mut index: HashMap<Vec<&str>, Vec<&Vec<String>>> = HashMap::new();
+            for row in &relations[relation] {
+                let key = bound.iter().map(|idx| row[*idx].as_str()).collect();
+                index.entry(key).or_default().push(row);
+            }
+
+            let mut joined = Vec::new();
+            for binding in bindings {
+                let key: Vec<&str> = bound
+                    .iter()
+                    .map(|idx| value(&terms[*idx], &binding))
+                    .collect();
+                for row in index.get(&key).into_iter().flatten() {
+                    let mut binding = binding.clone();
+                    for (term, value) in terms.iter().zip(row.iter()) {
+                        if let Term::Variable(variable) = term {
+                            binding.insert(variable.clone(), value.clone());
+                        }
+                    }
+                    joined.push(binding);
+                }
+            }
+            bindings = joined;
+        }
+
+        bindings
+            .into_iter()
+            .filter(|binding| {
+                body.iter().all(|literal| match literal {
+                    Literal::Positive(..) => true,
+                    Literal::Negative(relation, terms) => {
+                        let row: Vec<String> = terms
+                            .iter()
+                            .map(|term| value(term, binding).to_string())
+                            .collect();
+                        !relations[relation].contains(&row)
+                    }
+                    Literal::NotEqual(lhs, rhs) => value(lhs, binding) != value(rhs, binding),
+                })
+            })
+            .map(|binding| {
+                head.iter()
+                    .map(|term| value(term, &binding).to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn value<'a>(term: &'a Term, binding: &'a Bindings) -> &'a str {
+        match term {
+            Term::Variable(variable) => &binding[variable],
+            Term::Constant(constant) => constant,
+            Term::Wildcard => panic!("Wildcards are only allowed in positive literals"),
+        }
+    }
+
+    fn tokenize(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {}
+                '"' => {
+                    let mut token = String::from('"');
+                    for c in chars.by_ref() {
+                        token.push(c);
+                        if c == '"' {
+                            break;
+                        }
+                    }
+                    tokens.push(token);
+                }
+                ':' | '!' if chars.peek().is_some_and(|c| matches!(c, '-' | '=')) => {
+                    tokens.push(format!("{}{}", c, chars.next().unwrap()));
+                }
+                c if c.is_alphanumeric() || c == '_' => {
+                    let mut token = String::from(c);
+                    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                        token.push(c);
+                    }
+                    tokens.push(token);
+                }
+                c => tokens.push(c.to_string()),
+            }
+        }
+        tokens
+    }
+
+    struct Parser {
+        tokens: Vec<String>,
+        idx: usize,
+    }
+
+    impl Parser {
+        fn next(&mut self) -> &str {
+            self.idx += 1;
+            &self.tokens[self.idx - 1]
+        }
+
+        fn peek(&self, offset: usize) -> &str {
+            self.tokens
+                .get(self.idx + offset)
+                .map_or("", |token| token.as_str())
+        }
+
+        fn expect(&mut self, expected: &str) {
+            let token = self.next();
+            assert_eq!(token, expected, "Unexpected token in the datalog rules");
+        }
+
+        fn atom(&mut self) -> (String, Vec<Term>) {
+            let relation = self.next().to_string();
+            self.expect("(");
+            let mut terms = vec![self.term()];
+            while self.peek(0) == "," {
+                self.next();
+                terms.push(self.term());
+            }
+            self.expect(")");
+            (relation, terms)
+        }
+
+        fn term(&mut self) -> Term {
+            match self.next() {
+                "_" => Term::Wildcard,
+                token if token.starts_with('"') => {
+                    Term::Constant(token.trim_matches('"').to_string())
+                }
+                token => Term::Variable(token.to_string()),
+            }
+        }
+
+        // Parses alternatives separated by `;`, into the conjunctions of literals they expand to.
+        fn disjunction(&mut self) -> Vec<Vec<Literal>> {
+            let mut disjunction = self.conjunction();
+            while self.peek(0) == ";" {
+                self.next();
+                disjunction.extend(self.conjunction());
+            }
+            disjunction
+        }
+
+        fn conjunction(&mut self) -> Vec<Vec<Literal>> {
+            let mut conjunction = self.literal();
+            while self.peek(0) == "," {
+                self.next();
+                let alternatives = self.literal();
+                conjunction = conjunction
+                    .iter()
+                    .flat_map(|lhs| {
+                        alternatives
+                            .iter()
+                            .map(move |rhs| lhs.iter().chain(rhs).cloned().collect::<Vec<_>>())
+                    })
+                    .collect();
+            }
+            conjunction
+        }
+
+        fn literal(&mut self) -> Vec<Vec<Literal>> {
+            match self.peek(0) {
+                "(" => {
+                    self.next();
+                    let disjunction = self.disjunction();
+                    self.expect(")");
+                    disjunction
+                }
+                "!" => {
+                    self.next();
+                    let (relation, terms) = self.atom();
+                    vec![vec![Literal::Negative(relation, terms)]]
+                }
+                _ if self.peek(1) == "!=" => {
+                    let lhs = self.term();
+                    self.expect("!=");
+                    let rhs = self.term();
+                    vec![vec![Literal::NotEqual(lhs, rhs)]]
+                }
+                _ => {
+                    let (relation, terms) = self.atom();
+                    vec![vec![Literal::Positive(relation, terms)]]
+                }
+            }
+        }
+    }
+}
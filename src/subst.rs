@@ -0,0 +1,247 @@
+//! Substitution of generic parameters, used to instantiate the types of generic
+//! struct fields and function signatures.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+/// Maps the generic parameters of a struct or function to the origins and types they are
+/// instantiated with.
+#[derive(Clone, Debug, Default)]
+pub struct Substitution {
+    origins: HashMap<Name, Name>,
+    tys: HashMap<Name, Ty>,
+}
+
+impl Substitution {
+    /// Creates the substitution of the given generic declarations by the given parameters,
+    /// pair-wise: e.g. from `struct Ref<'a, T>` and the type `Ref<'r, i32>`.
+    pub fn new(generic_decls: &[GenericDecl], parameters: &[Parameter]) -> Self {
+        assert_eq!(
+            generic_decls.len(),
+            parameters.len(),
+            "Mismatched number of generic parameters: {:?} and {:?}",
+            generic_decls,
+            parameters,
+        );
+
+        let mut subst = Self::default();
+        for (decl, param) in generic_decls.iter().zip(parameters) {
+            match (decl, param) {
+                (GenericDecl::Origin(name), Parameter::Origin(origin)) => {
+                    subst.bind_origin(name, origin.clone());
+                }
+                (GenericDecl::Ty { name, .. }, Parameter::Ty(ty)) => {
+                    subst.bind_ty(name, ty.clone());
+                }
+                _ => panic!(
+                    "Mismatched kinds of generic decl {:?} and parameter {:?}",
+                    decl, param
+                ),
+            }
+        }
+        subst
+    }
+
+    pub fn bind_origin(&mut self, name: &Name, origin: Name) {
+        self.origins.insert(name.clone(), origin);
+    }
+
+    pub fn bind_ty(&mut self, name: &Name, ty: Ty) {
+        self.tys.insert(name.clone(), ty);
+    }
+
+    /// Infers the generic type parameters present in `generic_ty`, by matching it with `ty`, and
+    /// binds the ones that are still unknown: e.g. matching `&'a Vec<T>` with `&'v Vec<i32>`
+    /// infers that `T` is `i32`.
+    pub fn infer_tys(&mut self, generic_ty: &Ty, ty: &Ty) {
+        match (generic_ty, ty) {
+            (Ty::Param { name }, _) => {
+                self.tys.entry(name.clone()).or_insert_with(|| ty.clone());
+            }
+
+            (Ty::Ref { ty: generic_ty, .. }, Ty::Ref { ty, .. })
+            | (Ty::RefMut { ty: generic_ty, .. }, Ty::RefMut { ty, .. })
+            | (Ty::RawPtr { ty: generic_ty }, Ty::RawPtr { ty })
+            | (Ty::RawPtrMut { ty: generic_ty }, Ty::RawPtrMut { ty }) => {
+                self.infer_tys(generic_ty, ty);
+            }
+
+            (
+                Ty::Struct {
+                    parameters: generic_parameters,
+                    ..
+                },
+                Ty::Struct { parameters, .. },
+            ) => {
+                for (generic_param, param) in generic_parameters.iter().zip(parameters) {
+                    if let (Parameter::Ty(generic_ty), Parameter::Ty(ty)) = (generic_param, param) {
+                        self.infer_tys(generic_ty, ty);
+                    }
+                }
+            }
+
+            (
+                Ty::FnPtr {
+                    arg_tys: generic_arg_tys,
+                    ret_ty: generic_ret_ty,
+                    ..
+                },
+                Ty::FnPtr {
+                    arg_tys, ret_ty, ..
+                },
+            ) => {
+                for (generic_ty, ty) in generic_arg_tys.iter().zip(arg_tys) {
+                    self.infer_tys(generic_ty, ty);
+                }
+                self.infer_tys(generic_ret_ty, ret_ty);
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Returns the origin substituted for `origin`, or `origin` itself if it is not one of
+    /// the generic parameters.
+    pub fn apply_to_origin(&self, origin: &Name) -> Name {
+        self.origins.get(origin).unwrap_or(origin).clone()
+    }
+
+    /// Returns a copy of `ty` where all the generic parameters are substituted. The
+    /// parameters missing from this substitution are left as-is.
+    pub fn apply_to_ty(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Ref { origin, ty } => Ty::Ref {
+                origin: self.apply_to_origin(origin),
+                ty: Box::new(self.apply_to_ty(ty)),
+            },
+
+            Ty::RefMut { origin, ty } => Ty::RefMut {
+                origin: self.apply_to_origin(origin),
+                ty: Box::new(self.apply_to_ty(ty)),
+            },
+
+            Ty::RawPtr { ty } => Ty::RawPtr {
+                ty: Box::new(self.apply_to_ty(ty)),
+            },
+
+            Ty::RawPtrMut { ty } => Ty::RawPtrMut {
+                ty: Box::new(self.apply_to_ty(ty)),
+            },
+
+            Ty::Struct { name, parameters } => Ty::Struct {
+                name: name.clone(),
+                parameters: parameters
+                    .iter()
+                    .map(|param| match param {
+                        Parameter::Origin(origin) => {
+                            Parameter::Origin(self.apply_to_origin(origin))
+                        }
+                        Parameter::Ty(ty) => Parameter::Ty(self.apply_to_ty(ty)),
+                    })
+                    .collect(),
+            },
+
+            Ty::Param { name } => self.tys.get(name).cloned().unwrap_or_else(|| ty.clone()),
+
+            Ty::FnPtr {
+                binder,
+                arg_tys,
+                ret_ty,
+            } => {
+                // The origins bound by the binder shadow the ones in this substitution.
+                let mut subst = self.clone();
+                for origin in binder {
+                    subst.origins.remove(origin);
+                }
+                Ty::FnPtr {
+                    binder: binder.clone(),
+                    arg_tys: arg_tys.iter().map(|ty| subst.apply_to_ty(ty)).collect(),
+                    ret_ty: Box::new(subst.apply_to_ty(ret_ty)),
+                }
+            }
+
+            Ty::Dyn { name, origin } => Ty::Dyn {
+                name: name.clone(),
+                origin: self.apply_to_origin(origin),
+            },
+
+            Ty::I32 | Ty::Bool | Ty::Unit => ty.clone(),
+        }
+    }
+}
+
+/// Resolves the names of the generic type parameters used in struct declarations, function
+/// prototypes, and the signatures and variables of analyzed functions: these are parsed as
+/// parameter-less structs, and are turned into `Ty::Param`s here.
+pub fn resolve_generic_params(program: &mut Program) {
+    for decl in &mut program.struct_decls {
+        let generics = generic_ty_names(&decl.generic_decls);
+        for field in &mut decl.field_decls {
+            resolve_generic_params_in_ty(&mut field.ty, &generics);
+        }
+    }
+
+    for proto in &mut program.fn_prototypes {
+        let generics = generic_ty_names(&proto.generic_decls);
+        for ty in proto.arg_tys.iter_mut().chain(Some(&mut proto.ret_ty)) {
+            resolve_generic_params_in_ty(ty, &generics);
+        }
+    }
+
+    for body in &mut program.bodies {
+        let signature = match &mut body.signature {
+            Some(signature) => signature,
+            None => continue,
+        };
+        let generics = generic_ty_names(&signature.generic_decls);
+        let signature_tys = signature
+            .arg_tys
+            .iter_mut()
+            .chain(Some(&mut signature.ret_ty));
+        let variable_tys = body.variables.iter_mut().map(|v| &mut v.ty);
+        for ty in signature_tys.chain(variable_tys) {
+            resolve_generic_params_in_ty(ty, &generics);
+        }
+    }
+}
+
+fn generic_ty_names(generic_decls: &[GenericDecl]) -> Vec<&Name> {
+    generic_decls
+        .iter()
+        .filter_map(|decl| match decl {
+            GenericDecl::Ty { name, .. } => Some(name),
+            GenericDecl::Origin(_) => None,
+        })
+        .collect()
+}
+
+fn resolve_generic_params_in_ty(ty: &mut Ty, generics: &[&Name]) {
+    match ty {
+        Ty::Ref { ty, .. } | Ty::RefMut { ty, .. } | Ty::RawPtr { ty } | Ty::RawPtrMut { ty } => {
+            resolve_generic_params_in_ty(ty, generics)
+        }
+
+        Ty::Struct { name, parameters } => {
+            if parameters.is_empty() && generics.contains(&&*name) {
+                *ty = Ty::Param { name: name.clone() };
+                return;
+            }
+
+            for param in parameters {
+                if let Parameter::Ty(ty) = param {
+                    resolve_generic_params_in_ty(ty, generics);
+                }
+            }
+        }
+
+        Ty::FnPtr {
+            arg_tys, ret_ty, ..
+        } => {
+            for ty in arg_tys.iter_mut().chain(Some(&mut **ret_ty)) {
+                resolve_generic_params_in_ty(ty, generics);
+            }
+        }
+
+        Ty::Param { .. } | Ty::Dyn { .. } | Ty::I32 | Ty::Bool | Ty::Unit => {}
+    }
+}
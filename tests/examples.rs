@@ -49,3 +49,48 @@ fn self_invalidation_loop_shared() -> eyre::Result<()> {
 fn diamond_ref_mod() -> eyre::Result<()> {
     polonius::test_harness("tests/diamond-ref-mod")
 }
+
+#[test]
+fn implied_bounds() -> eyre::Result<()> {
+    polonius::test_harness("tests/implied-bounds")
+}
+
+#[test]
+fn implied_bounds_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/implied-bounds-err")
+}
+
+#[test]
+fn static_outlives_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/static-outlives-err")
+}
+
+#[test]
+fn closure_capture_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/closure-capture-err")
+}
+
+#[test]
+fn return_local_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/return-local-err")
+}
+
+#[test]
+fn drop_live_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/drop-live-err")
+}
+
+#[test]
+fn partial_overwrite_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/partial-overwrite-err")
+}
+
+#[test]
+fn scope_end_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/scope-end-err")
+}
+
+#[test]
+fn multiple_functions_err() -> eyre::Result<()> {
+    polonius::test_harness("tests/multiple-functions-err")
+}